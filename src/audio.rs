@@ -0,0 +1,158 @@
+use std::f32::consts::TAU;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sine,
+}
+
+/// Tone generator playing while the buzzer is active.
+#[derive(Debug, Clone)]
+pub struct Beeper {
+    /// Frequency of the tone in Hz.
+    pub frequency: f32,
+    pub waveform: Waveform,
+    /// Between 0 and 1.
+    pub volume: f32,
+    pub muted: bool,
+    /// XO-CHIP audio pattern, played instead of the tone when set.
+    pub pattern: Option<[u8; 16]>,
+    /// XO-CHIP pitch of `pattern`, see `pattern_rate`.
+    pub pitch: u8,
+    phase: f32,
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Beeper {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 0.25,
+            muted: false,
+            pattern: None,
+            pitch: 64,
+            phase: 0.0,
+        }
+    }
+}
+
+impl Beeper {
+    /// Fills `out` with mono samples between -1 and 1, silence when the
+    /// buzzer is not `active`.
+    pub fn fill(&mut self, out: &mut [f32], sample_rate: u32, active: bool) {
+        if !active || self.muted {
+            out.fill(0.0);
+            self.phase = 0.0;
+            return;
+        }
+
+        // The phase goes through the whole 128 bits of a pattern at once
+        let step = match self.pattern {
+            Some(_) => pattern_rate(self.pitch) / 128.0 / sample_rate as f32,
+            None => self.frequency / sample_rate as f32,
+        };
+
+        for sample in out.iter_mut() {
+            let value = match (self.pattern, self.waveform) {
+                (Some(pattern), _) => {
+                    let bit = (self.phase * 128.0) as usize;
+
+                    if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                (None, Waveform::Square) if self.phase < 0.5 => 1.0,
+                (None, Waveform::Square) => -1.0,
+                (None, Waveform::Sine) => (self.phase * TAU).sin(),
+            };
+
+            *sample = value * self.volume;
+            self.phase = (self.phase + step).fract();
+        }
+    }
+}
+
+/// Bits per second at which an XO-CHIP audio pattern plays, 4000 at the
+/// default pitch of 64.
+pub fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+/// Writes mono samples between -1 and 1 as a 16-bit PCM WAV file.
+pub fn write_wav<W: Write>(out: &mut W, samples: &[f32], sample_rate: u32) -> io::Result<()> {
+    let data_len = samples.len() as u32 * 2;
+
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_len).to_le_bytes())?;
+    out.write_all(b"WAVE")?;
+
+    out.write_all(b"fmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?; // PCM
+    out.write_all(&1u16.to_le_bytes())?; // Mono
+    out.write_all(&sample_rate.to_le_bytes())?;
+    out.write_all(&(sample_rate * 2).to_le_bytes())?;
+    out.write_all(&2u16.to_le_bytes())?;
+    out.write_all(&16u16.to_le_bytes())?;
+
+    out.write_all(b"data")?;
+    out.write_all(&data_len.to_le_bytes())?;
+
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        out.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::{pattern_rate, write_wav, Beeper};
+
+    #[test]
+    fn test_beeper_square() {
+        let mut beeper = Beeper {
+            frequency: 1000.0,
+            volume: 0.5,
+            ..Beeper::default()
+        };
+        let mut out = [0.0; 8];
+
+        beeper.fill(&mut out, 8000, true);
+        assert_eq!(out, [0.5, 0.5, 0.5, 0.5, -0.5, -0.5, -0.5, -0.5]);
+
+        beeper.fill(&mut out, 8000, false);
+        assert_eq!(out, [0.0; 8]);
+    }
+
+    #[test]
+    fn test_beeper_pattern() {
+        let mut beeper = Beeper {
+            volume: 1.0,
+            pattern: Some([0xA0; 16]),
+            ..Beeper::default()
+        };
+        let mut out = [0.0; 4];
+
+        assert_eq!(pattern_rate(64), 4000.0);
+        assert_eq!(pattern_rate(112), 8000.0);
+
+        beeper.fill(&mut out, 4000, true);
+        assert_eq!(out, [1.0, -1.0, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_wav_header() {
+        let mut out = Vec::new();
+        write_wav(&mut out, &[0.0, 1.0], 44100).unwrap();
+
+        assert_eq!(out.len(), 44 + 4);
+        assert_eq!(&out[0..4], b"RIFF");
+        assert_eq!(&out[40..44], &4u32.to_le_bytes());
+        assert_eq!(&out[46..48], &i16::MAX.to_le_bytes());
+    }
+}
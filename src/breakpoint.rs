@@ -0,0 +1,407 @@
+use std::fmt;
+
+use crate::chip8::Chip8;
+use crate::instr::bytes_to_word;
+
+/// Where `Chip8::run` stops, see `Chip8::breakpoints`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Before the instruction at `addr` runs, if `condition` holds.
+    Address {
+        addr: u16,
+        condition: Option<Condition>,
+    },
+    /// After an instruction writes to memory between `start` and `end`
+    /// (inclusive).
+    Watch { start: u16, end: u16 },
+    /// Before any instruction with `opcode & mask == value`, e.g. every
+    /// `Dxyn` with a mask of 0xF000 and a value of 0xD000.
+    Opcode { mask: u16, value: u16 },
+}
+
+impl Breakpoint {
+    /// A break on the opcodes matching `pattern`, see `opcode_pattern`.
+    pub fn opcode(pattern: &str) -> Option<Breakpoint> {
+        let (mask, value) = opcode_pattern(pattern)?;
+
+        Some(Breakpoint::Opcode { mask, value })
+    }
+}
+
+/// Parses an opcode pattern where hex digits must match and anything else is
+/// a wildcard, e.g. `Dxyn` or `8xyE`. Returns the mask and the value an
+/// opcode must have once masked.
+pub fn opcode_pattern(pattern: &str) -> Option<(u16, u16)> {
+    if pattern.chars().count() != 4 {
+        return None;
+    }
+
+    let pattern = pattern
+        .chars()
+        .fold((0, 0), |(mask, value), c| match c.to_digit(16) {
+            Some(digit) => (mask << 4 | 0xF, value << 4 | digit as u16),
+            None => (mask << 4, value << 4),
+        });
+
+    Some(pattern)
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Breakpoint::Address { addr, condition } => {
+                write!(f, "{:#05X}", addr)?;
+                match condition {
+                    Some(condition) => write!(f, " if {}", condition),
+                    None => Ok(()),
+                }
+            }
+            Breakpoint::Watch { start, end } => {
+                write!(f, "write to {:#05X}-{:#05X}", start, end)
+            }
+            Breakpoint::Opcode { mask, value } => {
+                let pattern: String = (0..4)
+                    .rev()
+                    .map(|nibble| match (mask >> (nibble * 4)) & 0xF {
+                        0 => '_',
+                        _ => char::from_digit(((value >> (nibble * 4)) & 0xF) as u32, 16)
+                            .unwrap()
+                            .to_ascii_uppercase(),
+                    })
+                    .collect();
+
+                write!(f, "opcode {}", pattern)
+            }
+        }
+    }
+}
+
+/// A comparison of a register with a value, e.g. `V3 == 0x05`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub cmp: Cmp,
+    pub value: u16,
+}
+
+impl Condition {
+    pub fn holds(&self, chip8: &Chip8) -> bool {
+        let register = match self.register {
+            Register::V(x) => chip8.v[x & 0xF] as u16,
+            Register::I => chip8.i,
+            Register::Dt => chip8.delay_timer as u16,
+            Register::St => chip8.sound_timer as u16,
+        };
+
+        match self.cmp {
+            Cmp::Eq => register == self.value,
+            Cmp::Ne => register != self.value,
+            Cmp::Lt => register < self.value,
+            Cmp::Gt => register > self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {:#04X}", self.register, self.cmp, self.value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    V(usize),
+    I,
+    Dt,
+    St,
+}
+
+impl Register {
+    pub const ALL: [Register; 19] = [
+        Register::V(0),
+        Register::V(1),
+        Register::V(2),
+        Register::V(3),
+        Register::V(4),
+        Register::V(5),
+        Register::V(6),
+        Register::V(7),
+        Register::V(8),
+        Register::V(9),
+        Register::V(10),
+        Register::V(11),
+        Register::V(12),
+        Register::V(13),
+        Register::V(14),
+        Register::V(15),
+        Register::I,
+        Register::Dt,
+        Register::St,
+    ];
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+}
+
+impl Cmp {
+    pub const ALL: [Cmp; 4] = [Cmp::Eq, Cmp::Ne, Cmp::Lt, Cmp::Gt];
+}
+
+impl fmt::Display for Cmp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Cmp::Eq => "==",
+            Cmp::Ne => "!=",
+            Cmp::Lt => "<",
+            Cmp::Gt => ">",
+        })
+    }
+}
+
+/// A breakpoint which stopped `Chip8::run`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hit {
+    pub breakpoint: Breakpoint,
+    /// Address of the instruction about to run, or of the one which wrote
+    /// to memory for a watchpoint.
+    pub pc: u16,
+    /// Memory written, for a watchpoint.
+    pub address: Option<usize>,
+}
+
+impl fmt::Display for Hit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.address {
+            Some(address) => write!(
+                f,
+                "{:#05X} wrote to {:#05X} ({})",
+                self.pc, address, self.breakpoint
+            ),
+            None => write!(f, "{} at {:#05X}", self.breakpoint, self.pc),
+        }
+    }
+}
+
+impl Chip8 {
+    /// The address or opcode breakpoint matching the next instruction.
+    ///
+    /// An instruction is only stopped at once until another one is reached,
+    /// so that resuming runs it, even when it waits and runs again.
+    pub(crate) fn break_before(&mut self) -> Option<Hit> {
+        if self.break_pc == Some(self.pc) {
+            return None;
+        }
+        self.break_pc = None;
+
+        let opcode = match (self.mem.get(self.pc), self.mem.get(self.pc.wrapping_add(1))) {
+            (Ok(h), Ok(l)) => Some(bytes_to_word(h, l)),
+            _ => None,
+        };
+
+        let breakpoint = *self
+            .breakpoints
+            .iter()
+            .find(|breakpoint| match **breakpoint {
+                Breakpoint::Address { addr, condition } => {
+                    addr == self.pc && condition.is_none_or(|condition| condition.holds(self))
+                }
+                Breakpoint::Opcode { mask, value } => opcode.is_some_and(|op| op & mask == value),
+                Breakpoint::Watch { .. } => false,
+            })?;
+
+        self.break_pc = Some(self.pc);
+
+        Some(Hit {
+            breakpoint,
+            pc: self.pc,
+            address: None,
+        })
+    }
+
+    /// The watchpoint covering a write of the instruction at `pc` which just
+    /// ran.
+    pub(crate) fn break_after(&self, pc: u16) -> Option<Hit> {
+        self.mem.writes.iter().find_map(|&address| {
+            let breakpoint = *self
+                .breakpoints
+                .iter()
+                .find(|breakpoint| match **breakpoint {
+                    Breakpoint::Watch { start, end } => {
+                        (start as usize..=end as usize).contains(&address)
+                    }
+                    _ => false,
+                })?;
+
+            Some(Hit {
+                breakpoint,
+                pc,
+                address: Some(address),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::breakpoint::{Breakpoint, Cmp, Condition, Hit, Register};
+    use crate::chip8::Chip8;
+
+    // 200: LD V0, 0; 202: ADD V0, 1; 204: LD I, 0x300; 206: LD B, V0;
+    // 208: DRW V0, V0, 1; 20A: JP 0x202
+    const ROM: [u8; 12] = [
+        0x60, 0x00, 0x70, 0x01, 0xA3, 0x00, 0xF0, 0x33, 0xD0, 0x01, 0x12, 0x02,
+    ];
+
+    fn chip8(breakpoint: Breakpoint) -> Chip8 {
+        let mut chip8 = Chip8::from_rom(&ROM).unwrap();
+        chip8.quirks.display_wait = false;
+        chip8.breakpoints.push(breakpoint);
+        chip8.paused = false;
+        chip8
+    }
+
+    #[test]
+    fn test_address_breakpoint() {
+        let breakpoint = Breakpoint::Address {
+            addr: 0x204,
+            condition: None,
+        };
+        let mut chip8 = chip8(breakpoint);
+
+        let hit = chip8.run(100).unwrap().unwrap();
+        assert_eq!(
+            hit,
+            Hit {
+                breakpoint,
+                pc: 0x204,
+                address: None
+            }
+        );
+        assert_eq!((chip8.pc, chip8.cycles), (0x204, 2));
+        assert!(chip8.paused);
+
+        // Resuming runs the instruction it stopped at
+        chip8.paused = false;
+        assert_eq!(chip8.run(100).unwrap().unwrap().pc, 0x204);
+        assert_eq!(chip8.cycles, 7);
+    }
+
+    #[test]
+    fn test_resume_waiting_instruction() {
+        let breakpoint = Breakpoint::Address {
+            addr: 0x202,
+            condition: None,
+        };
+
+        // I = 0x050; loop: DRW V0, V0, 1; JP loop
+        let mut chip8 = Chip8::from_rom(&[0xA0, 0x50, 0xD0, 0x01, 0x12, 0x02]).unwrap();
+        chip8.quirks.display_wait = true;
+        chip8.breakpoints.push(breakpoint);
+        chip8.paused = false;
+        assert_eq!(chip8.run(100).unwrap().unwrap().pc, 0x202);
+
+        chip8.paused = false;
+        assert_eq!(chip8.run(100).unwrap().unwrap().pc, 0x202);
+        assert_eq!(chip8.gfx.iter().filter(|&&p| p != 0).count(), 4);
+
+        // Waiting for the display does not stop again
+        chip8.paused = false;
+        assert_eq!(chip8.run(100).unwrap(), None);
+        chip8.tick_timers();
+        assert_eq!(chip8.run(100).unwrap().unwrap().pc, 0x202);
+        assert!(chip8.gfx.iter().all(|&p| p == 0));
+
+        // loop: LD V0, K; JP loop
+        let breakpoint = Breakpoint::Address {
+            addr: 0x200,
+            condition: None,
+        };
+        let mut chip8 = Chip8::from_rom(&[0xF0, 0x0A, 0x12, 0x00]).unwrap();
+        chip8.breakpoints.push(breakpoint);
+        chip8.paused = false;
+        assert_eq!(chip8.run(100).unwrap().unwrap().pc, 0x200);
+
+        chip8.paused = false;
+        assert_eq!(chip8.run(100).unwrap(), None);
+        chip8.set_key(0x5, true);
+        chip8.set_key(0x5, false);
+        assert_eq!(chip8.run(100).unwrap().unwrap().pc, 0x200);
+        assert_eq!(chip8.v[0], 0x5);
+    }
+
+    #[test]
+    fn test_conditional_breakpoint() {
+        let mut chip8 = chip8(Breakpoint::Address {
+            addr: 0x202,
+            condition: Some(Condition {
+                register: Register::V(0),
+                cmp: Cmp::Eq,
+                value: 3,
+            }),
+        });
+
+        assert!(chip8.run(100).unwrap().is_some());
+        assert_eq!((chip8.pc, chip8.v[0]), (0x202, 3));
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let breakpoint = Breakpoint::Watch {
+            start: 0x301,
+            end: 0x302,
+        };
+        let mut chip8 = chip8(breakpoint);
+
+        let hit = chip8.run(100).unwrap().unwrap();
+        assert_eq!(
+            hit,
+            Hit {
+                breakpoint,
+                pc: 0x206,
+                address: Some(0x301)
+            }
+        );
+        assert_eq!(chip8.pc, 0x208);
+    }
+
+    #[test]
+    fn test_opcode_breakpoint() {
+        let breakpoint = Breakpoint::opcode("Dxyn").unwrap();
+        assert_eq!(
+            breakpoint,
+            Breakpoint::Opcode {
+                mask: 0xF000,
+                value: 0xD000
+            }
+        );
+        assert_eq!(breakpoint.to_string(), "opcode D___");
+        assert_eq!(
+            Breakpoint::opcode("8xyE").unwrap().to_string(),
+            "opcode 8__E"
+        );
+        assert_eq!(Breakpoint::opcode("D12"), None);
+
+        let mut chip8 = chip8(breakpoint);
+        assert_eq!(chip8.run(100).unwrap().unwrap().pc, 0x208);
+
+        // Stepping ignores breakpoints
+        chip8.execute().unwrap();
+        assert_eq!(chip8.pc, 0x20A);
+    }
+}
@@ -1,40 +1,40 @@
 use crate::{
+    breakpoint::{Breakpoint, Hit},
+    error::{ExecError, Fault, LoadError, MemFault},
+    hash::fnv1a,
     instr::{bytes_to_word, split_into_4bits, Instructions},
-    mem::Mem,
+    mem::{Mem, PROGRAM_START},
+    quirks::Quirks,
+    rng::Rng,
+    trace::Tracer,
+    variant::Variant,
 };
 
 pub const WIDTH: usize = 64;
 pub const HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+/// Default `stack_depth`.
+pub const STACK_SIZE: usize = 16;
 
-/*
-_________________
-| 1 | 2 | 3 | 4 |
------------------
-| Q | W | E | R |
------------------
-| A | S | D | F |
------------------
-| Z | X | C | V |
------------------
-*/
-// const KEYMAP: [Scancode; 16] = [
-//     Scancode::X,
-//     Scancode::Num0,
-//     Scancode::Num2,
-//     Scancode::Num3,
-//     Scancode::Q,
-//     Scancode::W,
-//     Scancode::E,
-//     Scancode::A,
-//     Scancode::S,
-//     Scancode::D,
-//     Scancode::Z,
-//     Scancode::C,
-//     Scancode::Num4,
-//     Scancode::R,
-//     Scancode::F,
-//     Scancode::V,
-// ];
+/// A sprite drawn by `Dxyn`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Draw {
+    /// Where the sprite was read from, `I` at the time.
+    pub addr: u16,
+    pub x: usize,
+    pub y: usize,
+    /// 8 pixels, or 16 for a SUPER-CHIP `Dxy0`.
+    pub width: usize,
+    pub rows: usize,
+    /// `Chip8::planes` at the time, the sprite of each plane drawn follows
+    /// the previous one in memory.
+    pub planes: u8,
+    /// Whether a lit pixel was erased, the value of VF after the draw.
+    pub collision: bool,
+    /// `Chip8::cycles` before the draw.
+    pub cycle: u64,
+}
 
 pub struct Chip8 {
     pub v: [u8; 16],
@@ -43,12 +43,55 @@ pub struct Chip8 {
     pub sound_timer: u8,
     pub pc: u16,
     // pub sp: u8,
+    /// Return addresses of the subroutines being executed, innermost last.
     pub stack: Vec<u16>,
+    /// Number of nested `2nnn` calls before a stack overflow, 12 on the
+    /// COSMAC VIP and 16 on most later interpreters.
+    pub stack_depth: usize,
     pub mem: Mem,
     pub keys: [bool; 16],
-    pub gfx: [bool; HEIGHT * WIDTH],
+    /// `width() * height()` pixels stored row by row, each one a bitmask of
+    /// the planes it is lit in (only the first plane before XO-CHIP).
+    pub gfx: Vec<u8>,
+    /// SUPER-CHIP 128x64 mode.
+    pub hires: bool,
     pub paused: bool,
+    /// Register waiting for a key in `Fx0A`.
     pub target_register: Option<usize>,
+    /// First key pressed while waiting in `Fx0A`.
+    pub pressed_key: Option<u8>,
+    /// Set once `pressed_key` is released, it completes `Fx0A`.
+    pub released_key: Option<u8>,
+    pub quirks: Quirks,
+    /// Set at every 60 Hz tick, cleared by `Dxyn` when
+    /// `Quirks::display_wait` is enabled.
+    pub vblank: bool,
+    pub variant: Variant,
+    /// SUPER-CHIP RPL user flags, saved and restored by `Fx75` and `Fx85`.
+    pub rpl: [u8; 16],
+    /// Bitmask of the planes drawn to, scrolled and cleared, selected by the
+    /// XO-CHIP `Fn01` instruction.
+    pub planes: u8,
+    /// XO-CHIP 1-bit audio samples loaded by `F002`, the buzzer plays a plain
+    /// tone until then.
+    pub pattern: Option<[u8; 16]>,
+    /// XO-CHIP playback rate of `pattern`, set by `Fx3A`.
+    pub pitch: u8,
+    /// Instructions executed successfully since the machine started.
+    pub cycles: u64,
+    /// Last sprite drawn, for debuggers.
+    pub last_draw: Option<Draw>,
+    /// Where `run` stops, `execute` ignores them.
+    pub breakpoints: Vec<Breakpoint>,
+    /// `pc` where `run` last stopped at a breakpoint, until another
+    /// instruction is reached.
+    pub(crate) break_pc: Option<u16>,
+    /// Records each instruction `execute` runs.
+    pub trace: Option<Tracer>,
+    /// Draws the numbers of `Cxkk`, seeded randomly unless replaced.
+    pub rng: Rng,
+    /// FNV-1a hash of the ROM the machine was started from, if known.
+    pub rom_hash: Option<u64>,
 }
 
 impl Chip8 {
@@ -58,55 +101,208 @@ impl Chip8 {
             i: 0,
             delay_timer: 0,
             sound_timer: 0,
-            pc: 0x200,
+            pc: PROGRAM_START as u16,
             // sp: 0,
-            stack: Vec::with_capacity(16),
+            stack: Vec::with_capacity(STACK_SIZE),
+            stack_depth: STACK_SIZE,
             mem,
             keys: [false; 16],
-            gfx: [false; HEIGHT * WIDTH],
+            gfx: vec![0; WIDTH * HEIGHT],
+            hires: false,
             paused: true,
             target_register: None,
+            pressed_key: None,
+            released_key: None,
+            quirks: Quirks::default(),
+            vblank: true,
+            variant: Variant::default(),
+            rpl: [0; 16],
+            planes: 1,
+            pattern: None,
+            pitch: 64,
+            cycles: 0,
+            last_draw: None,
+            breakpoints: Vec::new(),
+            break_pc: None,
+            trace: None,
+            rng: Rng::from_entropy(),
+            rom_hash: None,
+        }
+    }
+
+    /// Creates a machine with `rom` loaded at 0x200, ready to run.
+    pub fn from_rom(rom: &[u8]) -> Result<Self, LoadError> {
+        Self::from_rom_with_variant(rom, Variant::Chip8)
+    }
+
+    /// Like `from_rom`, with the memory size of `variant`.
+    pub fn from_rom_with_variant(rom: &[u8], variant: Variant) -> Result<Self, LoadError> {
+        let mut mem = Mem::with_size(variant.ram_size());
+        mem.load_rom(rom)?;
+
+        let mut chip8 = Chip8::new(mem);
+        chip8.variant = variant;
+        chip8.rom_hash = Some(fnv1a(rom));
+
+        Ok(chip8)
+    }
+
+    /// Runs up to `cycles` instructions, stopping early if the machine gets
+    /// paused (by the user, by an error or by a breakpoint).
+    ///
+    /// The timers are not ticked, `tick_timers` must be called 60 times per
+    /// second, as `Clock` does. Until then, with the `display_wait` quirk of
+    /// the default COSMAC VIP quirks, the second `Dxyn` waits forever.
+    ///
+    /// Returns the breakpoint which paused the machine, if any.
+    pub fn run(&mut self, cycles: usize) -> Result<Option<Hit>, ExecError> {
+        for _ in 0..cycles {
+            if self.paused {
+                break;
+            }
+
+            let pc = self.pc;
+            let hit = match self.break_before() {
+                Some(hit) => Some(hit),
+                None => {
+                    self.execute()?;
+                    self.break_after(pc)
+                }
+            };
+
+            if hit.is_some() {
+                self.paused = true;
+                return Ok(hit);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The display, `width() * height()` pixels stored row by row. Each pixel
+    /// is a bitmask of the planes it is lit in, so 0 to 3.
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.gfx
+    }
+
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_WIDTH
+        } else {
+            WIDTH
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            HEIGHT
+        }
+    }
+
+    /// Switches between the 64x32 and 128x64 modes, clearing the display.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.gfx = vec![0; self.width() * self.height()];
+    }
+
+    /// Whether the next instruction is a jump to itself (`1nnn` with `nnn`
+    /// equal to its own address), the usual way ROMs end, or the SUPER-CHIP
+    /// `00FD` exit instruction.
+    pub fn is_halted(&self) -> bool {
+        match (self.mem.get(self.pc), self.mem.get(self.pc.wrapping_add(1))) {
+            (Ok(h), Ok(l)) => {
+                let opcode = bytes_to_word(h, l);
+
+                opcode == 0x1000 | self.pc || (opcode == 0x00FD && self.variant != Variant::Chip8)
+            }
+            _ => false,
         }
     }
 
-    pub fn main_loop(&mut self) {
-        // let lines = [
-        //     Point::new(0, (HEIGHT * 4) as i32 + 1),
-        //     Point::new((WIDTH * 4) as i32 + 1, (HEIGHT * 4) as i32 + 1),
-        //     Point::new((WIDTH * 4) as i32 + 1, 0),
-        // ];
+    /// Updates the state of one of the 16 keys of the hex keypad.
+    ///
+    /// Like on the COSMAC VIP, `Fx0A` completes when a key is pressed and
+    /// then released while it waits.
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        let key = key & 0xF;
+        self.keys[key as usize] = pressed;
 
-        'running: loop {
-            // for (i, scancode) in KEYMAP.iter().enumerate() {
-            //     self.keys[i] = event_pump.keyboard_state().is_scancode_pressed(*scancode);
+        if self.target_register.is_some() {
+            if pressed {
+                self.pressed_key.get_or_insert(key);
+            } else if self.pressed_key == Some(key) {
+                self.released_key = Some(key);
+            }
+        }
+    }
 
-            //     if self.paused && self.keys[i] {
-            //         self.v[self.target_register.unwrap()] = i as u8;
-            //         self.target_register = None;
-            //         self.paused = false;
-            //     }
-            // }
+    /// Sets every key which changed with `set_key`, in order.
+    pub fn set_keys(&mut self, keys: [bool; 16]) {
+        for (key, &pressed) in keys.iter().enumerate() {
+            if pressed != self.keys[key] {
+                self.set_key(key as u8, pressed);
+            }
         }
     }
 
-    pub fn run(&mut self) {
-        if !self.paused {
-            self.execute();
+    /// Fetches, decodes and executes a single instruction.
+    ///
+    /// On error the machine is paused with `pc` left on the faulty
+    /// instruction, so it can be inspected.
+    pub fn execute(&mut self) -> Result<(), ExecError> {
+        let pc = self.pc;
+        let traced = self.trace_before();
+        self.mem.writes.clear();
+        let result = self.fetch_and_execute();
+
+        match result {
+            Ok(()) => {
+                self.cycles += 1;
+
+                for &address in &self.mem.writes {
+                    self.mem.written_at[address] = self.cycles;
+                }
+
+                if let Some(entry) = traced {
+                    self.trace_after(entry);
+                }
+            }
+            Err(_) => {
+                self.pc = pc;
+                self.paused = true;
+            }
         }
+
+        result
     }
 
-    pub fn execute(&mut self) {
-        let (h, l) = (self.mem.get(self.pc), self.mem.get(self.pc + 1));
+    fn fetch_and_execute(&mut self) -> Result<(), ExecError> {
+        let pc = self.pc;
+        let fetch = |fault: MemFault| Fault::from(fault).at(pc, 0);
+        let h = self.mem.get(pc).map_err(fetch)?;
+        let l = self.mem.get(pc.wrapping_add(1)).map_err(fetch)?;
         let opcode = bytes_to_word(h, l);
 
-        self.pc += 2;
+        self.pc = pc.wrapping_add(2);
+
+        let at = |fault: Fault| fault.at(pc, opcode);
+
+        if self.variant == Variant::XoChip && self.execute_xochip(opcode).map_err(at)? {
+            return Ok(());
+        }
+
+        if self.variant != Variant::Chip8 && self.execute_superchip(opcode) {
+            return Ok(());
+        }
 
         match split_into_4bits(opcode) {
             (0, 0, 0xE, 0) => self.cls(),
-            (0, 0, 0xE, 0xE) => self.ret(),
+            (0, 0, 0xE, 0xE) => self.ret().map_err(at)?,
             (0, _, _, _) => self.sys_addr(),
             (1, _, _, _) => self.jp_addr(opcode),
-            (2, _, _, _) => self.call_addr(opcode),
+            (2, _, _, _) => self.call_addr(opcode).map_err(at)?,
             (3, _, _, _) => self.se_vx_kk(opcode),
             (4, _, _, _) => self.sne_vx_kk(opcode),
             (5, _, _, _) => self.se_vx_vy(opcode),
@@ -125,7 +321,7 @@ impl Chip8 {
             (0xA, _, _, _) => self.ld_i_addr(opcode),
             (0xB, _, _, _) => self.jp_v0_addr(opcode),
             (0xC, _, _, _) => self.rnd_vx_kk(opcode),
-            (0xD, _, _, _) => self.drw_vx_vy_nibble(opcode),
+            (0xD, _, _, _) => self.drw_vx_vy_nibble(opcode).map_err(at)?,
             (0xE, _, 9, 0xE) => self.skp_vx(opcode),
             (0xE, _, 0xA, 1) => self.sknp_vx(opcode),
             (0xF, _, 0, 7) => self.ld_vx_dt(opcode),
@@ -134,26 +330,255 @@ impl Chip8 {
             (0xF, _, 1, 8) => self.ld_st_vx(opcode),
             (0xF, _, 1, 0xE) => self.add_i_vx(opcode),
             (0xF, _, 2, 9) => self.ld_f_vx(opcode),
-            (0xF, _, 3, 3) => self.ld_b_vx(opcode),
-            (0xF, _, 5, 5) => self.ld_addri_vx(opcode),
-            (0xF, _, 6, 5) => self.ld_vx_addri(opcode),
+            (0xF, _, 3, 3) => self.ld_b_vx(opcode).map_err(at)?,
+            (0xF, _, 5, 5) => self.ld_addri_vx(opcode).map_err(at)?,
+            (0xF, _, 6, 5) => self.ld_vx_addri(opcode).map_err(at)?,
+
+            _ => return Err(ExecError::UnknownOpcode { pc, opcode }),
+        }
+
+        Ok(())
+    }
+
+    /// Executes the instructions added by SUPER-CHIP, returns false for the
+    /// other ones.
+    fn execute_superchip(&mut self, opcode: u16) -> bool {
+        match split_into_4bits(opcode) {
+            (0, 0, 0xC, _) => self.scd_nibble(opcode),
+            (0, 0, 0xF, 0xB) => self.scr(),
+            (0, 0, 0xF, 0xC) => self.scl(),
+            (0, 0, 0xF, 0xD) => self.exit(),
+            (0, 0, 0xF, 0xE) => self.low(),
+            (0, 0, 0xF, 0xF) => self.high(),
+            (0xF, _, 3, 0) => self.ld_hf_vx(opcode),
+            (0xF, _, 7, 5) => self.ld_r_vx(opcode),
+            (0xF, _, 8, 5) => self.ld_vx_r(opcode),
+            _ => return false,
+        }
+
+        true
+    }
 
-            _ => panic!("Unimplemented: {:#06X}", opcode),
+    /// Executes the instructions added by XO-CHIP, returns false for the
+    /// other ones.
+    fn execute_xochip(&mut self, opcode: u16) -> Result<bool, Fault> {
+        match split_into_4bits(opcode) {
+            (0, 0, 0xD, _) => self.scu_nibble(opcode),
+            (5, _, _, 2) => self.ld_addri_vx_vy(opcode)?,
+            (5, _, _, 3) => self.ld_vx_vy_addri(opcode)?,
+            (0xF, 0, 0, 0) => self.ld_i_long()?,
+            (0xF, _, 0, 1) => self.plane_n(opcode),
+            (0xF, 0, 0, 2) => self.audio()?,
+            (0xF, _, 3, 0xA) => self.pitch_vx(opcode),
+            _ => return Ok(false),
         }
 
-        self.tick_timers();
+        Ok(true)
+    }
+
+    /// Skips the next instruction, which is 4 bytes long if it is the XO-CHIP
+    /// `F000 nnnn`.
+    pub fn skip(&mut self) {
+        let long = self.variant == Variant::XoChip
+            && self.mem.get(self.pc) == Ok(0xF0)
+            && self.mem.get(self.pc.wrapping_add(1)) == Ok(0x00);
+
+        self.pc = self.pc.wrapping_add(if long { 4 } else { 2 });
+    }
+
+    /// Runs the instruction which just ran again, for the instructions which
+    /// wait. `pc` wraps around below 0, after one at the end of the 64 KiB of
+    /// XO-CHIP memory.
+    pub(crate) fn repeat(&mut self) {
+        self.pc = self.pc.wrapping_sub(2);
     }
 
-    fn tick_timers(&mut self) {
+    /// Decrements the delay and sound timers, to be called at 60 Hz
+    /// (see `Clock`).
+    pub fn tick_timers(&mut self) {
+        self.vblank = true;
+
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
 
         if self.sound_timer > 0 {
-            // audio.start_beep();
             self.sound_timer -= 1;
-        } else {
-            // audio.stop_beep();
         }
     }
+
+    /// Whether the buzzer sounds, which is as long as the sound timer runs.
+    pub fn buzzer_active(&self) -> bool {
+        self.sound_timer > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::error::ExecError;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_unknown_opcode_halts() {
+        let mut chip8 = Chip8::from_rom(&[0x60, 0x01, 0xFF, 0xFF]).unwrap();
+        chip8.paused = false;
+
+        let err = chip8.run(10).unwrap_err();
+
+        assert_eq!(
+            err,
+            ExecError::UnknownOpcode {
+                pc: 0x202,
+                opcode: 0xFFFF
+            }
+        );
+        assert_eq!(chip8.pc, 0x202);
+        assert!(chip8.paused);
+    }
+
+    #[test]
+    fn test_stack_errors() {
+        let mut chip8 = Chip8::from_rom(&[0x00, 0xEE]).unwrap();
+        assert!(matches!(
+            chip8.execute(),
+            Err(ExecError::StackUnderflow { pc: 0x200, .. })
+        ));
+
+        let mut chip8 = Chip8::from_rom(&[0x22, 0x00]).unwrap();
+        chip8.paused = false;
+        assert!(matches!(
+            chip8.run(100),
+            Err(ExecError::StackOverflow { pc: 0x200, .. })
+        ));
+        assert_eq!(chip8.stack.len(), 16);
+    }
+
+    #[test]
+    fn test_stack_depth() {
+        // CALL 0x202, CALL 0x204, CALL 0x206
+        let mut chip8 = Chip8::from_rom(&[0x22, 0x02, 0x22, 0x04, 0x22, 0x06]).unwrap();
+        chip8.stack_depth = 2;
+        chip8.paused = false;
+
+        assert_eq!(
+            chip8.run(3),
+            Err(ExecError::StackOverflow {
+                pc: 0x204,
+                opcode: 0x2206
+            })
+        );
+        assert_eq!(chip8.stack, [0x202, 0x204]);
+    }
+
+    #[test]
+    fn test_memory_fault_reports_location() {
+        // I = 0xFFE, LD [I], V2
+        let mut chip8 = Chip8::from_rom(&[0xAF, 0xFE, 0xF2, 0x55]).unwrap();
+        chip8.execute().unwrap();
+
+        assert_eq!(
+            chip8.execute(),
+            Err(ExecError::MemoryOutOfBounds {
+                pc: 0x202,
+                opcode: 0xF255,
+                address: 0x1000
+            })
+        );
+    }
+
+    #[test]
+    fn test_memory_writes() {
+        // I = 0x300, LD V0, 0xFF; LD B, V0
+        let mut chip8 = Chip8::from_rom(&[0xA3, 0x00, 0x60, 0xFF, 0xF0, 0x33]).unwrap();
+        for _ in 0..3 {
+            chip8.execute().unwrap();
+        }
+
+        assert_eq!(chip8.mem.writes, [0x300, 0x301, 0x302]);
+        assert_eq!(chip8.mem.written_at[0x300..0x304], [3, 3, 3, 0]);
+
+        // The next instruction (0000) writes nothing
+        chip8.execute().unwrap();
+        assert!(chip8.mem.writes.is_empty());
+    }
+
+    #[test]
+    fn test_wait_for_key_release() {
+        // LD V3, K
+        let mut chip8 = Chip8::from_rom(&[0xF3, 0x0A]).unwrap();
+        chip8.set_key(0x7, true);
+        chip8.execute().unwrap();
+        chip8.set_key(0x7, false);
+        chip8.execute().unwrap();
+
+        // Keys held before the wait are ignored
+        assert_eq!(chip8.pc, 0x200);
+        assert_eq!(chip8.target_register, Some(3));
+
+        chip8.set_key(0xA, true);
+        chip8.execute().unwrap();
+        assert_eq!(chip8.pc, 0x200);
+
+        chip8.set_key(0xA, false);
+        chip8.execute().unwrap();
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.v[3], 0xA);
+        assert_eq!(chip8.target_register, None);
+    }
+
+    #[test]
+    fn test_wait_at_end_of_memory() {
+        // LD V3, K in the last two bytes of the XO-CHIP memory
+        let mut chip8 = Chip8::from_rom_with_variant(&[], Variant::XoChip).unwrap();
+        chip8.mem.ram[0xFFFE..].copy_from_slice(&[0xF3, 0x0A]);
+        chip8.pc = 0xFFFE;
+
+        chip8.execute().unwrap();
+        assert_eq!(chip8.pc, 0xFFFE);
+
+        // DRW V0, V0, 1 waiting for the display
+        chip8.mem.ram[0xFFFE..].copy_from_slice(&[0xD0, 0x01]);
+        chip8.quirks.display_wait = true;
+        chip8.vblank = false;
+        chip8.execute().unwrap();
+        assert_eq!(chip8.pc, 0xFFFE);
+
+        // EXIT
+        chip8.mem.ram[0xFFFE..].copy_from_slice(&[0x00, 0xFD]);
+        chip8.execute().unwrap();
+        assert_eq!(chip8.pc, 0xFFFE);
+    }
+
+    #[test]
+    fn test_superchip_exit() {
+        let rom = [0x00, 0xFD];
+
+        // A plain CHIP-8 ignores it like any other 0nnn
+        let mut chip8 = Chip8::from_rom(&rom).unwrap();
+        assert!(!chip8.is_halted());
+        chip8.execute().unwrap();
+        assert_eq!(chip8.pc, 0x202);
+
+        let mut chip8 = Chip8::from_rom(&rom).unwrap();
+        chip8.variant = Variant::SuperChip;
+        chip8.paused = false;
+        assert!(chip8.is_halted());
+        chip8.run(10).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+        assert!(chip8.paused);
+    }
+
+    #[test]
+    fn test_xochip_long_load() {
+        // SE V0, 0 skips the whole F000 nnnn
+        let rom = [0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0xF0, 0x00, 0xAB, 0xCD];
+        let mut chip8 = Chip8::from_rom_with_variant(&rom, Variant::XoChip).unwrap();
+        assert_eq!(chip8.mem.ram.len(), 0x10000);
+
+        chip8.execute().unwrap();
+        assert_eq!(chip8.pc, 0x206);
+        chip8.execute().unwrap();
+        assert_eq!((chip8.pc, chip8.i), (0x20A, 0xABCD));
+    }
 }
@@ -0,0 +1,78 @@
+use std::fs;
+
+use chipr::trace::{diverge, Entry};
+
+use super::Args;
+
+/// Entries shown before and after the divergence.
+pub const DEFAULT_CONTEXT: usize = 3;
+
+/// Exits with 0 if the traces match, 1 if they diverge and 2 on errors, like
+/// `diff`.
+pub fn main(args: &[String]) -> i32 {
+    match run(args) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(err) => {
+            eprintln!("chipr diff-trace: {}", err);
+            2
+        }
+    }
+}
+
+/// Whether the traces match.
+fn run(args: &[String]) -> Result<bool, String> {
+    let args = Args::parse(args, &["context"])?;
+    let (a_path, b_path) = match args.positional.as_slice() {
+        [a, b] => (a, b),
+        _ => return Err("expected exactly two trace paths".to_string()),
+    };
+    let context = args.parsed("context")?.unwrap_or(DEFAULT_CONTEXT);
+
+    let a = read_trace(a_path)?;
+    let b = read_trace(b_path)?;
+
+    let divergence = match diverge(&a, &b) {
+        Some(divergence) => divergence,
+        None => {
+            println!("Traces match ({} instructions)", a.len());
+            return Ok(true);
+        }
+    };
+    let index = divergence.index;
+
+    match a.get(index).or_else(|| b.get(index)) {
+        Some(entry) => println!(
+            "Traces diverge at cycle {} (entry {}, pc {:04X}):",
+            entry.cycle,
+            index + 1,
+            entry.pc
+        ),
+        None => println!("Traces diverge at entry {}:", index + 1),
+    }
+    for difference in &divergence.differences {
+        println!("    {}", difference);
+    }
+
+    println!();
+    for entry in &a[index.saturating_sub(context)..index] {
+        println!("  {}", entry);
+    }
+    for (name, trace) in [("a", &a), ("b", &b)] {
+        for entry in trace.iter().skip(index).take(context + 1) {
+            println!("{} {}", name, entry);
+        }
+    }
+
+    Ok(false)
+}
+
+fn read_trace(path: &str) -> Result<Vec<Entry>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| Entry::parse(line).map_err(|e| format!("{}:{}: {}", path, n + 1, e)))
+        .collect()
+}
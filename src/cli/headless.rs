@@ -0,0 +1,283 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+
+use chipr::audio::{write_wav, Beeper};
+use chipr::breakpoint::opcode_pattern;
+use chipr::chip8::STACK_SIZE;
+use chipr::clock::{DEFAULT_CPU_HZ, TIMER_HZ};
+use chipr::display::{ascii_art, write_pbm};
+use chipr::hash::fnv1a;
+use chipr::movie::{Movie, Playback};
+use chipr::rng::Rng;
+use chipr::trace::{Filter, Format, Tracer};
+use chipr::{AddressMode, Breakpoint, Chip8, Clock, Font, Quirks, Variant};
+
+use super::Args;
+
+pub const DEFAULT_CYCLES: u64 = 1_000_000;
+const SAMPLE_RATE: u32 = 44100;
+
+pub fn main(args: &[String]) -> i32 {
+    match run(args) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("chipr headless: {}", err);
+            1
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let args = Args::parse(
+        args,
+        &[
+            "break",
+            "cycles",
+            "font",
+            "hz",
+            "memory",
+            "out",
+            "quirks",
+            "record",
+            "replay",
+            "seed",
+            "stack-depth",
+            "trace",
+            "trace-format",
+            "trace-opcodes",
+            "trace-pc",
+            "variant",
+            "wav",
+        ],
+    )?;
+    let rom_path = match args.positional.as_slice() {
+        [path] => path,
+        _ => return Err("expected exactly one ROM path".to_string()),
+    };
+    let max_cycles = args.parsed("cycles")?.unwrap_or(DEFAULT_CYCLES);
+    let hz = args.parsed("hz")?.unwrap_or(DEFAULT_CPU_HZ);
+    if hz == 0 {
+        return Err("--hz must be at least 1".to_string());
+    }
+    let mut clock = Clock::new(hz);
+    let stack_depth = args.parsed("stack-depth")?.unwrap_or(STACK_SIZE);
+    if stack_depth == 0 {
+        return Err("--stack-depth must be at least 1".to_string());
+    }
+    let address_mode = match args.value("memory") {
+        None | Some("fault") => AddressMode::Fault,
+        Some("wrap") => AddressMode::Wrap,
+        Some(other) => return Err(format!("invalid value for --memory: {}", other)),
+    };
+    let quirks = match args.value("quirks") {
+        None | Some("vip") => Quirks::COSMAC_VIP,
+        Some("chip48") => Quirks::CHIP_48,
+        Some("schip") => Quirks::SUPER_CHIP,
+        Some("xochip") => Quirks::XO_CHIP,
+        Some(other) => return Err(format!("invalid value for --quirks: {}", other)),
+    };
+    let variant = match args.value("variant") {
+        None | Some("chip8") => Variant::Chip8,
+        Some("schip") => Variant::SuperChip,
+        Some("xochip") => Variant::XoChip,
+        Some(other) => return Err(format!("invalid value for --variant: {}", other)),
+    };
+    let font = match args.value("font") {
+        None | Some("schip") => Font::SuperChip,
+        Some("vip") => Font::Vip,
+        Some("dream6800") => Font::Dream6800,
+        Some("eti660") => Font::Eti660,
+        Some(other) => return Err(format!("invalid value for --font: {}", other)),
+    };
+    let trace_format = match args.value("trace-format") {
+        None | Some("text") => Format::Text,
+        Some("json") => Format::Json,
+        Some(other) => return Err(format!("invalid value for --trace-format: {}", other)),
+    };
+    let trace_filter = Filter {
+        pc: args.value("trace-pc").map(parse_range).transpose()?,
+        opcodes: match args.value("trace-opcodes") {
+            Some(patterns) => patterns
+                .split(',')
+                .map(|pattern| {
+                    opcode_pattern(pattern)
+                        .ok_or_else(|| format!("invalid value for --trace-opcodes: {}", pattern))
+                })
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        },
+    };
+
+    let seed = args.parsed("seed")?;
+    if args.value("record").is_some() && args.value("replay").is_some() {
+        return Err("--record and --replay cannot be used together".to_string());
+    }
+
+    let rom = fs::read(rom_path).map_err(|e| format!("{}: {}", rom_path, e))?;
+    let mut playback = None;
+    let mut chip8 = match args.value("replay") {
+        // The movie brings its own settings
+        Some(path) => {
+            let movie = Movie::load(Path::new(path)).map_err(|e| format!("{}: {}", path, e))?;
+            if movie.cpu_hz == 0 {
+                return Err(format!("{}: invalid hz 0", path));
+            }
+            let chip8 = movie.start(&rom).map_err(|e| format!("{}: {}", path, e))?;
+
+            clock.cpu_hz = movie.cpu_hz;
+            playback = Some(Playback::replay(movie));
+            chip8
+        }
+        None => {
+            let mut chip8 = Chip8::from_rom_with_variant(&rom, variant)
+                .map_err(|e| format!("{}: {}", rom_path, e))?;
+            chip8.mem.address_mode = address_mode;
+            chip8.quirks = quirks;
+            chip8.stack_depth = stack_depth;
+            chip8.mem.set_font(font);
+            chip8.paused = false;
+            if let Some(seed) = seed {
+                chip8.rng = Rng::new(seed);
+            }
+            chip8
+        }
+    };
+
+    if args.value("record").is_some() {
+        playback = Some(Playback::record(Movie::new(fnv1a(&rom), &chip8, font, hz)));
+    }
+
+    if let Some(addresses) = args.value("break") {
+        chip8.breakpoints = addresses
+            .split(',')
+            .map(|addr| {
+                let addr = u16::from_str_radix(addr.trim_start_matches("0x"), 16)
+                    .map_err(|_| format!("invalid value for --break: {}", addr))?;
+                Ok(Breakpoint::Address {
+                    addr,
+                    condition: None,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+    }
+
+    if let Some(path) = args.value("trace") {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut tracer = Tracer::with_output(Box::new(BufWriter::new(file)), trace_format);
+        tracer.filter = trace_filter;
+        // Everything is in the file already
+        tracer.capacity = 0;
+        chip8.trace = Some(tracer);
+    }
+
+    let mut beeper = Beeper::default();
+    let mut samples = Vec::new();
+
+    // Frames run exactly as in the GUI, so a movie replays the same in both,
+    // and the run only stops between them
+    clock.playback = playback;
+    let mut failed = false;
+    let reason = loop {
+        if clock.playback.as_ref().is_some_and(Playback::finished) {
+            break "end of the movie".to_string();
+        }
+
+        if chip8.is_halted() {
+            break "halting loop".to_string();
+        }
+
+        // A movie presses keys later on
+        if chip8.target_register.is_some() && clock.playback.is_none() {
+            break "waiting for a key".to_string();
+        }
+
+        if chip8.cycles >= max_cycles {
+            break "cycle limit reached".to_string();
+        }
+
+        match clock.frame(&mut chip8) {
+            Ok(Some(hit)) => break format!("breakpoint {}", hit.breakpoint),
+            Ok(None) => {}
+            Err(err) => {
+                failed = true;
+                break err.to_string();
+            }
+        }
+
+        if args.value("wav").is_some() {
+            beeper.pattern = chip8.pattern;
+            beeper.pitch = chip8.pitch;
+            render_frame(&mut beeper, &mut samples, chip8.buzzer_active());
+        }
+    };
+
+    eprintln!(
+        "Stopped at {:#05X} after {} cycles: {}",
+        chip8.pc, chip8.cycles, reason
+    );
+
+    if let (Some(path), Some(tracer)) = (args.value("trace"), &mut chip8.trace) {
+        let result = match tracer.error.take() {
+            Some(e) => Err(e),
+            None => tracer.flush(),
+        };
+        result.map_err(|e| format!("{}: {}", path, e))?;
+    }
+
+    if let (Some(path), Some(playback)) = (args.value("record"), clock.playback) {
+        playback
+            .into_movie()
+            .save(Path::new(path))
+            .map_err(|e| format!("{}: {}", path, e))?;
+    }
+
+    match args.value("out") {
+        Some(path) => {
+            let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+            write_pbm(
+                &mut BufWriter::new(file),
+                chip8.framebuffer(),
+                chip8.width(),
+            )
+            .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        None => print!("{}", ascii_art(chip8.framebuffer(), chip8.width())),
+    }
+
+    if let Some(path) = args.value("wav") {
+        // Let the buzzer ring out, ROMs often beep right before halting
+        while chip8.buzzer_active() {
+            render_frame(&mut beeper, &mut samples, true);
+            chip8.tick_timers();
+        }
+
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        write_wav(&mut BufWriter::new(file), &samples, SAMPLE_RATE)
+            .map_err(|e| format!("{}: {}", path, e))?;
+    }
+
+    if failed {
+        return Err("stopped on an execution error".to_string());
+    }
+
+    Ok(())
+}
+
+/// Parses a `--trace-pc` range of hex addresses, e.g. `200-2FF`.
+fn parse_range(range: &str) -> Result<(u16, u16), String> {
+    let invalid = || format!("invalid value for --trace-pc: {}", range);
+    let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+    let start = u16::from_str_radix(start.trim_start_matches("0x"), 16).map_err(|_| invalid())?;
+    let end = u16::from_str_radix(end.trim_start_matches("0x"), 16).map_err(|_| invalid())?;
+
+    Ok((start, end))
+}
+
+/// Appends one 60 Hz frame of sound to `samples`.
+fn render_frame(beeper: &mut Beeper, samples: &mut Vec<f32>, active: bool) {
+    let start = samples.len();
+
+    samples.resize(start + (SAMPLE_RATE / TIMER_HZ) as usize, 0.0);
+    beeper.fill(&mut samples[start..], SAMPLE_RATE, active);
+}
@@ -0,0 +1,93 @@
+pub mod diff_trace;
+pub mod headless;
+
+pub fn print_usage() {
+    eprintln!(
+        "Usage:
+    chipr                       Start the GUI
+    chipr headless <rom> [opts] Run a ROM without a display
+    chipr diff-trace <a> <b> [--context <n>]
+                                Show where two --trace logs first differ,
+                                with n instructions around it (default: {})
+
+Headless options:
+    --cycles <n>    Instructions to execute, the run stops at the end of the frame
+                    reaching it (default: {})
+    --break <addrs> Stop before the instructions at comma separated hex addresses
+    --hz <n>        Instructions per second, timers tick every hz / 60 instructions
+                    (default: {})
+    --memory <mode> Out of bounds memory accesses: fault (default) or wrap
+    --quirks <name> Behavior of ambiguous instructions: vip (default), chip48, schip
+                    or xochip
+    --variant <name> Instruction set: chip8 (default), schip or xochip
+    --stack-depth <n> Nested calls before a stack overflow (default: {})
+    --font <name>   Hex digit sprites: schip (default), vip, dream6800 or eti660
+    --out <file>    Write the final display to a PBM file instead of stdout
+    --wav <file>    Render the sound of the run to a WAV file
+    --seed <n>      Seed of the random numbers drawn by Cxkk (default: random)
+    --record <file> Record the run to a movie file
+    --replay <file> Replay a movie file, with the settings it was recorded with
+    --trace <file>  Log every instruction executed to a file
+    --trace-format <fmt> Format of the trace: text (default) or json
+    --trace-pc <start>-<end> Only log the instructions between two hex addresses
+    --trace-opcodes <patterns> Only log the opcodes matching comma separated
+                    patterns, e.g. Dxyn,8xy4",
+        diff_trace::DEFAULT_CONTEXT,
+        headless::DEFAULT_CYCLES,
+        chipr::clock::DEFAULT_CPU_HZ,
+        chipr::chip8::STACK_SIZE
+    );
+}
+
+/// Minimal command line parser: positional arguments and `--option value` pairs.
+pub struct Args {
+    pub positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    /// Splits `args`, `options` lists the accepted `--option value` names.
+    pub fn parse(args: &[String], options: &[&str]) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut values = Vec::new();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                if !options.contains(&name) {
+                    return Err(format!("unknown option --{}", name));
+                }
+
+                match iter.next() {
+                    Some(value) => values.push((name.to_string(), value.clone())),
+                    None => return Err(format!("missing value for --{}", name)),
+                }
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+
+        Ok(Args {
+            positional,
+            options: values,
+        })
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn parsed<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.value(name) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid value for --{}: {}", name, value)),
+            None => Ok(None),
+        }
+    }
+}
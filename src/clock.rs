@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use crate::breakpoint::Hit;
+use crate::chip8::Chip8;
+use crate::error::ExecError;
+use crate::movie::Playback;
+
+/// Frequency of the delay and sound timers.
+pub const TIMER_HZ: u32 = 60;
+pub const DEFAULT_CPU_HZ: u32 = 700;
+
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / TIMER_HZ as u64);
+/// Longest stretch of wall time caught up in one call to `advance`, so a
+/// stalled frontend does not make the machine run in fast forward.
+const MAX_LAG: Duration = Duration::from_millis(250);
+
+/// Drives a `Chip8` in 60 Hz frames: each frame runs `cpu_hz / 60`
+/// instructions then ticks the timers once.
+///
+/// The number of instructions in a frame only depends on `cpu_hz` and the
+/// count of frames run, the wall time passed to `advance` only decides how
+/// many frames are run.
+pub struct Clock {
+    pub cpu_hz: u32,
+    /// Movie recorded or replayed as frames run.
+    pub playback: Option<Playback>,
+    frames: u64,
+    lag: Duration,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new(DEFAULT_CPU_HZ)
+    }
+}
+
+impl Clock {
+    pub fn new(cpu_hz: u32) -> Self {
+        Clock {
+            cpu_hz,
+            playback: None,
+            frames: 0,
+            lag: Duration::ZERO,
+        }
+    }
+
+    /// Counts frames from the start again, for a machine which just started.
+    pub fn reset(&mut self) {
+        self.frames = 0;
+        self.lag = Duration::ZERO;
+    }
+
+    /// Starts a new frame and returns its instruction budget.
+    ///
+    /// When `cpu_hz` is not a multiple of 60 the budget alternates so that
+    /// exactly `cpu_hz` instructions run every 60 frames.
+    pub fn next_frame(&mut self) -> usize {
+        let hz = self.cpu_hz as u64;
+        let ticks = TIMER_HZ as u64;
+        let cycles = (self.frames + 1) * hz / ticks - self.frames * hz / ticks;
+
+        self.frames += 1;
+
+        cycles as usize
+    }
+
+    /// Runs a single frame, unless the machine is paused or `playback` is a
+    /// finished replay. Returns the breakpoint it stopped at, if any.
+    pub fn frame(&mut self, chip8: &mut Chip8) -> Result<Option<Hit>, ExecError> {
+        self.frame_with(chip8, |_| {})
+    }
+
+    /// Like `frame`, calling `started` once the keys of the frame are set and
+    /// before any instruction runs, e.g. to record the state for rewinding.
+    pub fn frame_with(
+        &mut self,
+        chip8: &mut Chip8,
+        started: impl FnOnce(&Chip8),
+    ) -> Result<Option<Hit>, ExecError> {
+        if chip8.paused || self.playback.as_ref().is_some_and(Playback::finished) {
+            return Ok(None);
+        }
+
+        let cycles = self.next_frame();
+        if let Some(playback) = &mut self.playback {
+            playback.frame(chip8);
+        }
+        started(chip8);
+
+        let hit = chip8.run(cycles)?;
+        chip8.tick_timers();
+
+        Ok(hit)
+    }
+
+    /// The number of frames which fit in `elapsed` plus the time left over
+    /// from previous calls, without running them, e.g. to play frames
+    /// backwards at the same speed.
+    pub fn take_frames(&mut self, elapsed: Duration) -> usize {
+        self.lag = (self.lag + elapsed).min(MAX_LAG);
+
+        let frames = (self.lag.as_nanos() / FRAME.as_nanos()) as u32;
+        self.lag -= FRAME * frames;
+
+        frames as usize
+    }
+
+    /// Runs as many frames as fit in `elapsed` plus the time left over from
+    /// previous calls. Stops at the first breakpoint hit.
+    pub fn advance(
+        &mut self,
+        chip8: &mut Chip8,
+        elapsed: Duration,
+    ) -> Result<Option<Hit>, ExecError> {
+        self.advance_with(chip8, elapsed, |_| {})
+    }
+
+    /// Like `advance`, calling `started` at the start of each frame as
+    /// `frame_with` does.
+    pub fn advance_with(
+        &mut self,
+        chip8: &mut Chip8,
+        elapsed: Duration,
+        mut started: impl FnMut(&Chip8),
+    ) -> Result<Option<Hit>, ExecError> {
+        self.lag = (self.lag + elapsed).min(MAX_LAG);
+
+        while self.lag >= FRAME {
+            self.lag -= FRAME;
+
+            if let Some(hit) = self.frame_with(chip8, &mut started)? {
+                return Ok(Some(hit));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chip8::Chip8;
+    use crate::clock::Clock;
+
+    #[test]
+    fn test_frame_budget() {
+        let mut clock = Clock::new(700);
+        let cycles: usize = (0..60).map(|_| clock.next_frame()).sum();
+
+        assert_eq!(cycles, 700);
+    }
+
+    #[test]
+    fn test_take_frames() {
+        let mut clock = Clock::new(700);
+
+        assert_eq!(clock.take_frames(Duration::from_millis(40)), 2);
+        assert_eq!(clock.take_frames(Duration::from_millis(10)), 1);
+        assert_eq!(clock.take_frames(Duration::from_millis(5)), 0);
+    }
+
+    #[test]
+    fn test_timers_run_at_60hz() {
+        // 1200: JP 1200
+        let mut chip8 = Chip8::from_rom(&[0x12, 0x00]).unwrap();
+        let mut clock = Clock::new(1000);
+        chip8.paused = false;
+        chip8.delay_timer = 60;
+
+        for _ in 0..10 {
+            clock
+                .advance(&mut chip8, Duration::from_millis(50))
+                .unwrap();
+        }
+
+        assert_eq!(chip8.delay_timer, 30);
+    }
+}
@@ -0,0 +1,328 @@
+use crate::instr::{bytes_to_word, split_into_4bits};
+use crate::mem::PROGRAM_START;
+use crate::variant::Variant;
+
+/// A decoded instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: u16,
+    /// Size in bytes, 4 for the XO-CHIP `F000 nnnn`.
+    pub len: usize,
+    /// Mnemonic named after the `Instructions` method, e.g. `LD Vx, kk`.
+    pub text: String,
+}
+
+/// Decodes the instruction at `addr`, or `None` if the opcode is not part of
+/// `variant` or runs past the end of `ram`.
+pub fn decode(ram: &[u8], addr: usize, variant: Variant) -> Option<Instruction> {
+    let word = |addr: usize| Some(bytes_to_word(*ram.get(addr)?, *ram.get(addr + 1)?));
+    let opcode = word(addr)?;
+
+    if variant == Variant::XoChip && opcode == 0xF000 {
+        return Some(Instruction {
+            opcode,
+            len: 4,
+            text: format!("LD I, {:#06X}", word(addr + 2)?),
+        });
+    }
+
+    Some(Instruction {
+        opcode,
+        len: 2,
+        text: mnemonic(opcode, variant)?,
+    })
+}
+
+fn mnemonic(opcode: u16, variant: Variant) -> Option<String> {
+    // Same order as `Chip8::fetch_and_execute`
+    let extension = match variant {
+        Variant::XoChip => xochip_mnemonic(opcode).or_else(|| superchip_mnemonic(opcode)),
+        Variant::SuperChip => superchip_mnemonic(opcode),
+        Variant::Chip8 => None,
+    };
+
+    if extension.is_some() {
+        return extension;
+    }
+
+    let (_, x, y, n) = split_into_4bits(opcode);
+    let nnn = opcode & 0x0FFF;
+    let kk = opcode & 0x00FF;
+
+    let text = match split_into_4bits(opcode) {
+        (0, 0, 0xE, 0) => "CLS".to_string(),
+        (0, 0, 0xE, 0xE) => "RET".to_string(),
+        (0, _, _, _) => format!("SYS {:#05X}", nnn),
+        (1, _, _, _) => format!("JP {:#05X}", nnn),
+        (2, _, _, _) => format!("CALL {:#05X}", nnn),
+        (3, _, _, _) => format!("SE V{:X}, {:#04X}", x, kk),
+        (4, _, _, _) => format!("SNE V{:X}, {:#04X}", x, kk),
+        (5, _, _, _) => format!("SE V{:X}, V{:X}", x, y),
+        (6, _, _, _) => format!("LD V{:X}, {:#04X}", x, kk),
+        (7, _, _, _) => format!("ADD V{:X}, {:#04X}", x, kk),
+        (8, _, _, 0) => format!("LD V{:X}, V{:X}", x, y),
+        (8, _, _, 1) => format!("OR V{:X}, V{:X}", x, y),
+        (8, _, _, 2) => format!("AND V{:X}, V{:X}", x, y),
+        (8, _, _, 3) => format!("XOR V{:X}, V{:X}", x, y),
+        (8, _, _, 4) => format!("ADD V{:X}, V{:X}", x, y),
+        (8, _, _, 5) => format!("SUB V{:X}, V{:X}", x, y),
+        (8, _, _, 6) => format!("SHR V{:X}, V{:X}", x, y),
+        (8, _, _, 7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (8, _, _, 0xE) => format!("SHL V{:X}, V{:X}", x, y),
+        (9, _, _, 0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xA, _, _, _) => format!("LD I, {:#05X}", nnn),
+        (0xB, _, _, _) => format!("JP V0, {:#05X}", nnn),
+        (0xC, _, _, _) => format!("RND V{:X}, {:#04X}", x, kk),
+        (0xD, _, _, _) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xE, _, 9, 0xE) => format!("SKP V{:X}", x),
+        (0xE, _, 0xA, 1) => format!("SKNP V{:X}", x),
+        (0xF, _, 0, 7) => format!("LD V{:X}, DT", x),
+        (0xF, _, 0, 0xA) => format!("LD V{:X}, K", x),
+        (0xF, _, 1, 5) => format!("LD DT, V{:X}", x),
+        (0xF, _, 1, 8) => format!("LD ST, V{:X}", x),
+        (0xF, _, 1, 0xE) => format!("ADD I, V{:X}", x),
+        (0xF, _, 2, 9) => format!("LD F, V{:X}", x),
+        (0xF, _, 3, 3) => format!("LD B, V{:X}", x),
+        (0xF, _, 5, 5) => format!("LD [I], V{:X}", x),
+        (0xF, _, 6, 5) => format!("LD V{:X}, [I]", x),
+        _ => return None,
+    };
+
+    Some(text)
+}
+
+fn superchip_mnemonic(opcode: u16) -> Option<String> {
+    let (_, x, _, n) = split_into_4bits(opcode);
+
+    let text = match split_into_4bits(opcode) {
+        (0, 0, 0xC, _) => format!("SCD {}", n),
+        (0, 0, 0xF, 0xB) => "SCR".to_string(),
+        (0, 0, 0xF, 0xC) => "SCL".to_string(),
+        (0, 0, 0xF, 0xD) => "EXIT".to_string(),
+        (0, 0, 0xF, 0xE) => "LOW".to_string(),
+        (0, 0, 0xF, 0xF) => "HIGH".to_string(),
+        (0xF, _, 3, 0) => format!("LD HF, V{:X}", x),
+        (0xF, _, 7, 5) => format!("LD R, V{:X}", x),
+        (0xF, _, 8, 5) => format!("LD V{:X}, R", x),
+        _ => return None,
+    };
+
+    Some(text)
+}
+
+fn xochip_mnemonic(opcode: u16) -> Option<String> {
+    let (_, x, y, n) = split_into_4bits(opcode);
+
+    let text = match split_into_4bits(opcode) {
+        (0, 0, 0xD, _) => format!("SCU {}", n),
+        (5, _, _, 2) => format!("LD [I], V{:X}-V{:X}", x, y),
+        (5, _, _, 3) => format!("LD V{:X}-V{:X}, [I]", x, y),
+        (0xF, _, 0, 1) => format!("PLANE {}", x),
+        (0xF, 0, 0, 2) => "AUDIO".to_string(),
+        (0xF, _, 3, 0xA) => format!("PITCH V{:X}", x),
+        _ => return None,
+    };
+
+    Some(text)
+}
+
+/// One row of a `Disassembly`: an instruction, or a byte of data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line {
+    pub addr: usize,
+    pub len: usize,
+    pub code: bool,
+}
+
+/// Memory split into code and data.
+///
+/// Code is found by following every path from `PROGRAM_START` and the
+/// extra entry points, the rest is data. Computed jumps (`Bnnn`) are
+/// assumed to land on `nnn`, the other targets of a jump table can be added
+/// as entry points once they are seen running.
+pub struct Disassembly {
+    pub lines: Vec<Line>,
+}
+
+impl Disassembly {
+    pub fn new(ram: &[u8], variant: Variant, entries: &[usize]) -> Self {
+        let starts = reachable(ram, variant, entries);
+        let mut lines = Vec::new();
+        let mut addr = 0;
+
+        while addr < ram.len() {
+            let len = if starts[addr] {
+                decode(ram, addr, variant).map_or(1, |instr| instr.len)
+            } else {
+                1
+            };
+
+            lines.push(Line {
+                addr,
+                len,
+                code: starts[addr],
+            });
+            addr += len;
+        }
+
+        Disassembly { lines }
+    }
+
+    /// Row of the line covering `addr`.
+    pub fn row_of(&self, addr: usize) -> usize {
+        self.lines
+            .partition_point(|line| line.addr <= addr)
+            .saturating_sub(1)
+    }
+
+    /// Whether an instruction starts at `addr`.
+    pub fn is_code(&self, addr: usize) -> bool {
+        let line = self.lines[self.row_of(addr)];
+
+        line.code && line.addr == addr
+    }
+}
+
+/// Marks the start of every instruction reachable from `PROGRAM_START` and
+/// `entries`.
+fn reachable(ram: &[u8], variant: Variant, entries: &[usize]) -> Vec<bool> {
+    let mut starts = vec![false; ram.len()];
+    let mut todo = vec![PROGRAM_START];
+    todo.extend_from_slice(entries);
+
+    while let Some(addr) = todo.pop() {
+        if addr >= ram.len() || starts[addr] {
+            continue;
+        }
+
+        let instr = match decode(ram, addr, variant) {
+            Some(instr) => instr,
+            None => continue,
+        };
+        starts[addr] = true;
+
+        let next = addr + instr.len;
+        let nnn = (instr.opcode & 0x0FFF) as usize;
+
+        match split_into_4bits(instr.opcode) {
+            (0, 0, 0xE, 0xE) => {}
+            (0, 0, 0xF, 0xD) if variant != Variant::Chip8 => {}
+            (1, _, _, _) => todo.push(nnn),
+            (2, _, _, _) => todo.extend([nnn, next]),
+            (0xB, _, _, _) => todo.push(nnn),
+            (5, _, _, 2) | (5, _, _, 3) if variant == Variant::XoChip => todo.push(next),
+            (3, _, _, _) | (4, _, _, _) | (5, _, _, _) | (9, _, _, 0) => {
+                todo.extend([next, skip(ram, next, variant)])
+            }
+            (0xE, _, 9, 0xE) | (0xE, _, 0xA, 1) => todo.extend([next, skip(ram, next, variant)]),
+            _ => todo.push(next),
+        }
+    }
+
+    starts
+}
+
+/// Address after the instruction at `addr` is skipped, see `Chip8::skip`.
+fn skip(ram: &[u8], addr: usize, variant: Variant) -> usize {
+    let long = variant == Variant::XoChip && ram.get(addr..addr + 2) == Some(&[0xF0, 0x00]);
+
+    addr + if long { 4 } else { 2 }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disasm::{decode, Disassembly};
+    use crate::mem::{Mem, PROGRAM_START};
+    use crate::variant::Variant;
+
+    fn text(opcode: u16, variant: Variant) -> Option<String> {
+        decode(&opcode.to_be_bytes(), 0, variant).map(|instr| instr.text)
+    }
+
+    #[test]
+    fn test_decode() {
+        let cases = [
+            (0x00E0, "CLS"),
+            (0x00EE, "RET"),
+            (0x0123, "SYS 0x123"),
+            (0x1234, "JP 0x234"),
+            (0x2ABC, "CALL 0xABC"),
+            (0x3A0F, "SE VA, 0x0F"),
+            (0x5120, "SE V1, V2"),
+            (0x6F42, "LD VF, 0x42"),
+            (0x8AB4, "ADD VA, VB"),
+            (0x8AB6, "SHR VA, VB"),
+            (0x8ABE, "SHL VA, VB"),
+            (0xA123, "LD I, 0x123"),
+            (0xB300, "JP V0, 0x300"),
+            (0xC1FF, "RND V1, 0xFF"),
+            (0xD12F, "DRW V1, V2, 15"),
+            (0xE39E, "SKP V3"),
+            (0xE3A1, "SKNP V3"),
+            (0xF40A, "LD V4, K"),
+            (0xF429, "LD F, V4"),
+            (0xF455, "LD [I], V4"),
+            (0xF465, "LD V4, [I]"),
+        ];
+
+        for (opcode, expected) in cases {
+            assert_eq!(text(opcode, Variant::Chip8).unwrap(), expected);
+        }
+
+        assert_eq!(text(0xE1FF, Variant::Chip8), None);
+        assert_eq!(text(0xF0FF, Variant::XoChip), None);
+    }
+
+    #[test]
+    fn test_decode_variants() {
+        // SUPER-CHIP opcodes are SYS calls on the original CHIP-8
+        assert_eq!(text(0x00FF, Variant::Chip8).unwrap(), "SYS 0x0FF");
+        assert_eq!(text(0x00FF, Variant::SuperChip).unwrap(), "HIGH");
+        assert_eq!(text(0x00C4, Variant::SuperChip).unwrap(), "SCD 4");
+        assert_eq!(text(0xF330, Variant::SuperChip).unwrap(), "LD HF, V3");
+        assert_eq!(text(0xF375, Variant::XoChip).unwrap(), "LD R, V3");
+
+        assert_eq!(text(0x5122, Variant::SuperChip).unwrap(), "SE V1, V2");
+        assert_eq!(text(0x5122, Variant::XoChip).unwrap(), "LD [I], V1-V2");
+        assert_eq!(text(0x00D2, Variant::XoChip).unwrap(), "SCU 2");
+        assert_eq!(text(0xF201, Variant::XoChip).unwrap(), "PLANE 2");
+        assert_eq!(text(0xF002, Variant::XoChip).unwrap(), "AUDIO");
+
+        let long = decode(&[0xF0, 0x00, 0x12, 0x34], 0, Variant::XoChip).unwrap();
+        assert_eq!((long.text.as_str(), long.len), ("LD I, 0x1234", 4));
+        assert_eq!(decode(&[0xF0, 0x00], 0, Variant::XoChip), None);
+    }
+
+    #[test]
+    fn test_reachability() {
+        let rom = [
+            0x22, 0x08, // 200: CALL 0x208
+            0x30, 0x01, // 202: SE V0, 0x01
+            0x12, 0x02, // 204: JP 0x202
+            0x12, 0x06, // 206: JP 0x206
+            0xA2, 0x0E, // 208: LD I, 0x20E
+            0xD0, 0x11, // 20A: DRW V0, V1, 1
+            0x00, 0xEE, // 20C: RET
+            0x12, 0x0E, // 20E: only reached through a jump table
+        ];
+        let mut mem = Mem::new();
+        mem.load_rom(&rom).unwrap();
+
+        let disasm = Disassembly::new(&mem.ram, Variant::Chip8, &[]);
+
+        for addr in (PROGRAM_START..PROGRAM_START + 14).step_by(2) {
+            assert!(disasm.is_code(addr), "{:#X}", addr);
+        }
+        assert!(!disasm.is_code(0x20E));
+        assert!(!disasm.is_code(0x20F));
+        assert!(!disasm.is_code(0x201));
+
+        let row = disasm.row_of(0x20B);
+        assert_eq!(disasm.lines[row].addr, 0x20A);
+        assert_eq!(disasm.lines[row + 2].addr, 0x20E);
+
+        // Entry points add code the analysis cannot see
+        let disasm = Disassembly::new(&mem.ram, Variant::Chip8, &[0x20E]);
+        assert!(disasm.is_code(0x20E));
+    }
+}
@@ -0,0 +1,34 @@
+use std::io::{self, Write};
+
+/// Renders a framebuffer as text, one line per row, `#` for pixels lit in the
+/// first plane, `+` in the second one and `@` in both.
+pub fn ascii_art(gfx: &[u8], width: usize) -> String {
+    let mut out = String::with_capacity(gfx.len() + gfx.len() / width);
+
+    for row in gfx.chunks(width) {
+        out.extend(
+            row.iter()
+                .map(|&pixel| ['.', '#', '+', '@'][pixel as usize & 3]),
+        );
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Writes a framebuffer as a plain (P1) PBM image, pixels lit in any plane
+/// are black.
+pub fn write_pbm<W: Write>(out: &mut W, gfx: &[u8], width: usize) -> io::Result<()> {
+    writeln!(out, "P1")?;
+    writeln!(out, "{} {}", width, gfx.len() / width)?;
+
+    for row in gfx.chunks(width) {
+        let line: Vec<&str> = row
+            .iter()
+            .map(|&pixel| if pixel != 0 { "1" } else { "0" })
+            .collect();
+        writeln!(out, "{}", line.join(" "))?;
+    }
+
+    Ok(())
+}
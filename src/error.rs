@@ -0,0 +1,148 @@
+use std::error::Error;
+use std::fmt;
+
+/// Why an instruction could not be executed, without its location.
+///
+/// Returned by the fallible methods of `Instructions`, `Chip8::execute`
+/// turns it into an `ExecError`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    StackOverflow,
+    StackUnderflow,
+    Memory(MemFault),
+}
+
+impl Fault {
+    pub fn at(self, pc: u16, opcode: u16) -> ExecError {
+        match self {
+            Fault::StackOverflow => ExecError::StackOverflow { pc, opcode },
+            Fault::StackUnderflow => ExecError::StackUnderflow { pc, opcode },
+            Fault::Memory(MemFault { address }) => ExecError::MemoryOutOfBounds {
+                pc,
+                opcode,
+                address,
+            },
+        }
+    }
+}
+
+/// An access past the end of memory while `Mem::address_mode` is `Fault`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemFault {
+    pub address: usize,
+}
+
+impl From<MemFault> for Fault {
+    fn from(fault: MemFault) -> Self {
+        Fault::Memory(fault)
+    }
+}
+
+/// An error which halted the machine, `pc` is the address of the faulty
+/// instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecError {
+    UnknownOpcode {
+        pc: u16,
+        opcode: u16,
+    },
+    StackOverflow {
+        pc: u16,
+        opcode: u16,
+    },
+    StackUnderflow {
+        pc: u16,
+        opcode: u16,
+    },
+    MemoryOutOfBounds {
+        pc: u16,
+        opcode: u16,
+        address: usize,
+    },
+}
+
+impl ExecError {
+    pub fn pc(&self) -> u16 {
+        match *self {
+            ExecError::UnknownOpcode { pc, .. }
+            | ExecError::StackOverflow { pc, .. }
+            | ExecError::StackUnderflow { pc, .. }
+            | ExecError::MemoryOutOfBounds { pc, .. } => pc,
+        }
+    }
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ExecError::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {:#06X} at {:#05X}", opcode, pc)
+            }
+            ExecError::StackOverflow { pc, opcode } => {
+                write!(f, "stack overflow at {:#05X} ({:#06X})", pc, opcode)
+            }
+            ExecError::StackUnderflow { pc, opcode } => {
+                write!(f, "stack underflow at {:#05X} ({:#06X})", pc, opcode)
+            }
+            ExecError::MemoryOutOfBounds {
+                pc,
+                opcode,
+                address,
+            } => write!(
+                f,
+                "out of bounds memory access to {:#X} at {:#05X} ({:#06X})",
+                address, pc, opcode
+            ),
+        }
+    }
+}
+
+impl Error for ExecError {}
+
+/// A ROM which cannot be loaded into memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    RomTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            LoadError::RomTooLarge { size, max } => write!(
+                f,
+                "ROM is too large ({} bytes, at most {} bytes fit in memory)",
+                size, max
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+/// A save state which cannot be restored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    NotASaveState,
+    /// Saved by a newer version of the emulator.
+    UnsupportedVersion(u16),
+    Truncated,
+    Invalid,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            StateError::NotASaveState => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(version) => write!(
+                f,
+                "save state version {} is not supported (expected {})",
+                version,
+                crate::state::STATE_VERSION
+            ),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::Invalid => write!(f, "save state is corrupted"),
+        }
+    }
+}
+
+impl Error for StateError {}
@@ -0,0 +1,117 @@
+/// Hex digit sprites of the small font, 5 bytes per digit. Interpreters
+/// shipped slightly different ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Font {
+    /// COSMAC VIP interpreter.
+    Vip,
+    /// DREAM 6800, only 3 pixels wide.
+    Dream6800,
+    /// ETI-660, 3 pixels wide with lowercase b and d.
+    Eti660,
+    /// SUPER-CHIP, also used by CHIP-48 and most modern interpreters.
+    #[default]
+    SuperChip,
+}
+
+impl Font {
+    pub const ALL: [(&'static str, Font); 4] = [
+        ("COSMAC VIP", Font::Vip),
+        ("DREAM 6800", Font::Dream6800),
+        ("ETI-660", Font::Eti660),
+        ("SUPER-CHIP", Font::SuperChip),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        Font::ALL
+            .iter()
+            .find(|(_, font)| font == self)
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+
+    /// The 16 digits, 0 to F.
+    pub fn sprites(&self) -> &'static [u8; 80] {
+        match self {
+            Font::Vip => &VIP,
+            Font::Dream6800 => &DREAM_6800,
+            Font::Eti660 => &ETI_660,
+            Font::SuperChip => &SUPER_CHIP,
+        }
+    }
+}
+
+const VIP: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+const DREAM_6800: [u8; 80] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x40, 0x40, 0x40, 0x40, 0x40, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+const ETI_660: [u8; 80] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x20, 0x20, 0x20, 0x20, 0x20, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0x80, 0x80, 0xE0, 0xA0, 0xE0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0x20, 0x20, 0xE0, 0xA0, 0xE0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+const SUPER_CHIP: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
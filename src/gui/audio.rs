@@ -0,0 +1,72 @@
+use chipr::audio::Beeper;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{Device, Sample, SampleFormat, Stream, StreamConfig};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Output stream playing the beeper while the buzzer is active.
+pub struct Audio {
+    pub beeper: Arc<Mutex<Beeper>>,
+    active: Arc<AtomicBool>,
+    _stream: Stream,
+}
+
+impl Audio {
+    pub fn new() -> Result<Self, String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device")?;
+        let config = device.default_output_config().map_err(|e| e.to_string())?;
+
+        let beeper = Arc::new(Mutex::new(Beeper::default()));
+        let active = Arc::new(AtomicBool::new(false));
+
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config.into(), &beeper, &active),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config.into(), &beeper, &active),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config.into(), &beeper, &active),
+        }?;
+        stream.play().map_err(|e| e.to_string())?;
+
+        Ok(Audio {
+            beeper,
+            active,
+            _stream: stream,
+        })
+    }
+
+    pub fn set_active(&self, active: bool) {
+        self.active.store(active, Ordering::Relaxed);
+    }
+}
+
+fn build_stream<T: Sample>(
+    device: &Device,
+    config: &StreamConfig,
+    beeper: &Arc<Mutex<Beeper>>,
+    active: &Arc<AtomicBool>,
+) -> Result<Stream, String> {
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate.0;
+    let beeper = Arc::clone(beeper);
+    let active = Arc::clone(active);
+    let mut mono = Vec::new();
+
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                mono.resize(data.len() / channels, 0.0);
+                beeper
+                    .lock()
+                    .unwrap()
+                    .fill(&mut mono, sample_rate, active.load(Ordering::Relaxed));
+
+                for (frame, value) in data.chunks_mut(channels).zip(&mono) {
+                    frame.fill(T::from(value));
+                }
+            },
+            |e| eprintln!("Audio stream error: {}", e),
+        )
+        .map_err(|e| e.to_string())
+}
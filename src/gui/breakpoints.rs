@@ -0,0 +1,158 @@
+use eframe::egui;
+use eframe::epaint::Color32;
+
+use chipr::breakpoint::{Cmp, Condition, Register};
+use chipr::Breakpoint;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Address,
+    Watch,
+    Opcode,
+}
+
+/// List of breakpoints with a form to add more.
+pub struct BreakpointsEditor {
+    kind: Kind,
+    addr: String,
+    conditional: bool,
+    register: Register,
+    cmp: Cmp,
+    value: String,
+    end: String,
+    pattern: String,
+    error: Option<String>,
+}
+
+impl Default for BreakpointsEditor {
+    fn default() -> Self {
+        BreakpointsEditor {
+            kind: Kind::Address,
+            addr: "200".to_string(),
+            conditional: false,
+            register: Register::V(0),
+            cmp: Cmp::Eq,
+            value: "0".to_string(),
+            end: "200".to_string(),
+            pattern: "Dxyn".to_string(),
+            error: None,
+        }
+    }
+}
+
+impl BreakpointsEditor {
+    pub fn show(&mut self, ui: &mut egui::Ui, breakpoints: &mut Vec<Breakpoint>) {
+        let mut removed = None;
+        for (n, breakpoint) in breakpoints.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("x").clicked() {
+                    removed = Some(n);
+                }
+                ui.label(breakpoint.to_string());
+            });
+        }
+        if let Some(n) = removed {
+            breakpoints.remove(n);
+        }
+
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.kind, Kind::Address, "Address");
+            ui.radio_value(&mut self.kind, Kind::Watch, "Memory write");
+            ui.radio_value(&mut self.kind, Kind::Opcode, "Opcode");
+        });
+
+        ui.horizontal(|ui| {
+            match self.kind {
+                Kind::Address => {
+                    hex_field(ui, &mut self.addr);
+                    ui.checkbox(&mut self.conditional, "if");
+
+                    ui.add_enabled_ui(self.conditional, |ui| {
+                        egui::ComboBox::from_id_source("register")
+                            .width(40.0)
+                            .selected_text(self.register.to_string())
+                            .show_ui(ui, |ui| {
+                                for register in Register::ALL {
+                                    let name = register.to_string();
+                                    ui.selectable_value(&mut self.register, register, name);
+                                }
+                            });
+                        egui::ComboBox::from_id_source("cmp")
+                            .width(40.0)
+                            .selected_text(self.cmp.to_string())
+                            .show_ui(ui, |ui| {
+                                for cmp in Cmp::ALL {
+                                    ui.selectable_value(&mut self.cmp, cmp, cmp.to_string());
+                                }
+                            });
+                        hex_field(ui, &mut self.value);
+                    });
+                }
+                Kind::Watch => {
+                    hex_field(ui, &mut self.addr);
+                    ui.label("to");
+                    hex_field(ui, &mut self.end);
+                }
+                Kind::Opcode => {
+                    ui.add(egui::TextEdit::singleline(&mut self.pattern).desired_width(40.0));
+                }
+            }
+
+            if ui.button("Add").clicked() {
+                match self.parse() {
+                    Ok(breakpoint) => {
+                        breakpoints.push(breakpoint);
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(Color32::RED, error);
+        }
+    }
+
+    fn parse(&self) -> Result<Breakpoint, String> {
+        match self.kind {
+            Kind::Address => {
+                let condition = if self.conditional {
+                    Some(Condition {
+                        register: self.register,
+                        cmp: self.cmp,
+                        value: parse_hex(&self.value)?,
+                    })
+                } else {
+                    None
+                };
+
+                Ok(Breakpoint::Address {
+                    addr: parse_hex(&self.addr)?,
+                    condition,
+                })
+            }
+            Kind::Watch => {
+                let (start, end) = (parse_hex(&self.addr)?, parse_hex(&self.end)?);
+
+                if end < start {
+                    return Err(format!("{:#X} is before {:#X}", end, start));
+                }
+
+                Ok(Breakpoint::Watch { start, end })
+            }
+            Kind::Opcode => Breakpoint::opcode(&self.pattern)
+                .ok_or_else(|| format!("{} is not 4 characters long", self.pattern)),
+        }
+    }
+}
+
+fn hex_field(ui: &mut egui::Ui, text: &mut String) {
+    ui.add(egui::TextEdit::singleline(text).desired_width(40.0));
+}
+
+fn parse_hex(text: &str) -> Result<u16, String> {
+    let digits = text.trim().trim_start_matches("0x");
+
+    u16::from_str_radix(digits, 16).map_err(|_| format!("{} is not a hex number", text))
+}
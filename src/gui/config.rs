@@ -0,0 +1,16 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Directory holding the settings of the GUI, `None` if the environment
+/// gives no hint of where it should be.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+
+    base.map(|dir| dir.join("chipr"))
+}
@@ -0,0 +1,145 @@
+use eframe::egui::{self, RichText, Sense, TextStyle};
+use eframe::epaint::Color32;
+use std::ops::Range;
+
+use chipr::disasm::{decode, Disassembly};
+use chipr::{Breakpoint, Chip8, Variant};
+
+/// Background of the line about to run.
+const PC_COLOR: Color32 = Color32::from_rgb(0x30, 0x50, 0x80);
+
+/// Disassembly of the whole memory, scrolled to follow `pc`.
+pub struct DisasmView {
+    disassembly: Option<Disassembly>,
+    variant: Variant,
+    /// Memory when it was analysed, to notice code being overwritten.
+    analysed: Vec<u8>,
+    /// Addresses seen running that the analysis did not find.
+    entries: Vec<usize>,
+    /// Rows shown last frame.
+    visible: Range<usize>,
+    follow_pc: bool,
+}
+
+impl Default for DisasmView {
+    fn default() -> Self {
+        DisasmView {
+            disassembly: None,
+            variant: Variant::default(),
+            analysed: Vec::new(),
+            entries: Vec::new(),
+            visible: 0..0,
+            follow_pc: true,
+        }
+    }
+}
+
+impl DisasmView {
+    /// Forgets the analysis, for when another program is loaded.
+    pub fn reset(&mut self) {
+        self.disassembly = None;
+        self.entries.clear();
+    }
+
+    /// Shows the listing, returns the address of the line clicked.
+    pub fn show(&mut self, ui: &mut egui::Ui, chip8: &Chip8) -> Option<usize> {
+        let ram = &chip8.mem.ram;
+        let pc = chip8.pc as usize;
+
+        if self.variant != chip8.variant {
+            self.variant = chip8.variant;
+            self.disassembly = None;
+        }
+
+        // Code reached through computed jumps only shows up once it runs,
+        // and code can be rewritten by the program or the memory editor
+        let stale = self.disassembly.as_ref().is_none_or(|disasm| {
+            (!disasm.is_code(pc) && !self.entries.contains(&pc))
+                || self.analysed.len() != ram.len()
+                || disasm.lines.iter().any(|line| {
+                    let bytes = line.addr..line.addr + line.len;
+                    line.code && ram[bytes.clone()] != self.analysed[bytes]
+                })
+        });
+        if stale {
+            if pc < ram.len() && !self.entries.contains(&pc) {
+                self.entries.push(pc);
+            }
+            self.disassembly = Some(Disassembly::new(ram, chip8.variant, &self.entries));
+            self.analysed.clone_from(ram);
+        }
+        let disasm = self.disassembly.as_ref().unwrap();
+
+        ui.checkbox(&mut self.follow_pc, "Follow PC");
+
+        let row_height = ui.text_style_height(&TextStyle::Monospace);
+        let mut scroll = egui::ScrollArea::vertical().auto_shrink([false, false]);
+
+        let pc_row = disasm.row_of(pc);
+        let margin = self.visible.len() / 4;
+        let shown = self.visible.start + margin..self.visible.end.saturating_sub(margin);
+        if self.follow_pc && !shown.contains(&pc_row) {
+            let spacing = ui.spacing().item_spacing.y;
+            let centered = pc_row.saturating_sub(self.visible.len() / 2);
+            scroll = scroll.vertical_scroll_offset(centered as f32 * (row_height + spacing));
+        }
+
+        let breakpoints: Vec<usize> = chip8
+            .breakpoints
+            .iter()
+            .filter_map(|breakpoint| match *breakpoint {
+                Breakpoint::Address { addr, .. } => Some(addr as usize),
+                _ => None,
+            })
+            .collect();
+
+        let mut clicked = None;
+        scroll.show_rows(ui, row_height, disasm.lines.len(), |ui, rows| {
+            self.visible = rows.clone();
+
+            for line in &disasm.lines[rows] {
+                let text = match decode(ram, line.addr, chip8.variant).filter(|_| line.code) {
+                    Some(instr) => {
+                        let bytes = &ram[line.addr..line.addr + instr.len];
+                        let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+
+                        let mark = if breakpoints.contains(&line.addr) {
+                            '*'
+                        } else {
+                            ' '
+                        };
+
+                        RichText::new(format!(
+                            "{}{:04X}  {:<8}  {}",
+                            mark, line.addr, hex, instr.text
+                        ))
+                    }
+                    None => {
+                        let byte = ram[line.addr];
+                        let bits: String = (0..8)
+                            .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+                            .collect();
+
+                        RichText::new(format!(" {:04X}  {:02X}        {}", line.addr, byte, bits))
+                            .weak()
+                    }
+                };
+
+                let text = if line.addr == pc {
+                    text.monospace().background_color(PC_COLOR)
+                } else {
+                    text.monospace()
+                };
+
+                if ui
+                    .add(egui::Label::new(text).sense(Sense::click()))
+                    .clicked()
+                {
+                    clicked = Some(line.addr);
+                }
+            }
+        });
+
+        clicked
+    }
+}
@@ -0,0 +1,415 @@
+use eframe::egui::{self, Context, Event, Key};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Every key egui can report, used to name keys in the config file.
+const ALL_KEYS: [Key; 51] = [
+    Key::ArrowDown,
+    Key::ArrowLeft,
+    Key::ArrowRight,
+    Key::ArrowUp,
+    Key::Escape,
+    Key::Tab,
+    Key::Backspace,
+    Key::Enter,
+    Key::Space,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+];
+
+/// Host keys taken by the emulator, with what they do.
+const RESERVED: [(Key, &str); 1] = [(Key::Backspace, "rewinding")];
+
+/*
+Host keys of the hex keypad, indexed by CHIP-8 key.
+_________________      _________________
+| 1 | 2 | 3 | C |      | 1 | 2 | 3 | 4 |
+-----------------      -----------------
+| 4 | 5 | 6 | D |      | Q | W | E | R |
+-----------------  ->  -----------------
+| 7 | 8 | 9 | E |      | A | S | D | F |
+-----------------      -----------------
+| A | 0 | B | F |      | Z | X | C | V |
+-----------------      -----------------
+*/
+pub const QWERTY: Keymap = Keymap([
+    Key::X,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Q,
+    Key::W,
+    Key::E,
+    Key::A,
+    Key::S,
+    Key::D,
+    Key::Z,
+    Key::C,
+    Key::Num4,
+    Key::R,
+    Key::F,
+    Key::V,
+]);
+
+// Same physical keys as QWERTY on an AZERTY keyboard
+pub const AZERTY: Keymap = Keymap([
+    Key::X,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::A,
+    Key::Z,
+    Key::E,
+    Key::Q,
+    Key::S,
+    Key::D,
+    Key::W,
+    Key::C,
+    Key::Num4,
+    Key::R,
+    Key::F,
+    Key::V,
+]);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Keymap(pub [Key; 16]);
+
+impl Default for Keymap {
+    fn default() -> Self {
+        QWERTY
+    }
+}
+
+/// The default keymap and the ones specific to some ROMs, keyed by ROM hash.
+///
+/// Stored as text, one keymap per line:
+///
+/// ```text
+/// default X Num1 Num2 Num3 Q W E A S D Z C Num4 R F V
+/// rom 8c3d2ba1f00e4b27 X Num1 ArrowUp Num3 ArrowLeft W ArrowRight A ArrowDown D Z C Num4 R F V
+/// ```
+#[derive(Default)]
+pub struct Keymaps {
+    pub default: Keymap,
+    pub roms: HashMap<u64, Keymap>,
+}
+
+impl Keymaps {
+    /// Reads the keymaps from `path`, a missing file gives the defaults.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, self.to_text())
+    }
+
+    /// The keymap to use for the ROM with the given hash.
+    pub fn get(&self, rom: Option<u64>) -> &Keymap {
+        rom.and_then(|hash| self.roms.get(&hash))
+            .unwrap_or(&self.default)
+    }
+
+    pub fn get_mut(&mut self, rom: Option<u64>) -> &mut Keymap {
+        match rom {
+            Some(hash) if self.roms.contains_key(&hash) => self.roms.get_mut(&hash).unwrap(),
+            _ => &mut self.default,
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut keymaps = Self::default();
+
+        for (n, line) in text.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let error = |msg: &str| format!("line {}: {}", n + 1, msg);
+
+            match words.next() {
+                None => {}
+                Some(word) if word.starts_with('#') => {}
+                Some("default") => keymaps.default = parse_keymap(words).map_err(|e| error(&e))?,
+                Some("rom") => {
+                    let hash = words
+                        .next()
+                        .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+                        .ok_or_else(|| error("invalid ROM hash"))?;
+                    let keymap = parse_keymap(words).map_err(|e| error(&e))?;
+
+                    keymaps.roms.insert(hash, keymap);
+                }
+                Some(word) => return Err(error(&format!("unknown entry '{}'", word))),
+            }
+        }
+
+        Ok(keymaps)
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::from("# Host keys for the CHIP-8 keys 0 to F\n");
+        writeln!(text, "default {}", keymap_to_text(&self.default)).unwrap();
+
+        let mut roms: Vec<_> = self.roms.iter().collect();
+        roms.sort_by_key(|(hash, _)| **hash);
+
+        for (hash, keymap) in roms {
+            writeln!(text, "rom {:016x} {}", hash, keymap_to_text(keymap)).unwrap();
+        }
+
+        text
+    }
+}
+
+pub fn key_name(key: Key) -> String {
+    format!("{:?}", key)
+}
+
+fn key_from_name(name: &str) -> Option<Key> {
+    ALL_KEYS.iter().copied().find(|key| key_name(*key) == name)
+}
+
+/// What the emulator does with `key`, if it cannot be given to the game.
+fn reserved(key: Key) -> Option<&'static str> {
+    RESERVED
+        .iter()
+        .find(|(reserved, _)| *reserved == key)
+        .map(|(_, usage)| *usage)
+}
+
+fn parse_keymap<'a>(words: impl Iterator<Item = &'a str>) -> Result<Keymap, String> {
+    let keys = words
+        .map(|word| {
+            let key = key_from_name(word).ok_or(format!("unknown key '{}'", word))?;
+
+            match reserved(key) {
+                Some(usage) => Err(format!("key '{}' is reserved for {}", word, usage)),
+                None => Ok(key),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    keys.try_into()
+        .map(Keymap)
+        .map_err(|keys: Vec<Key>| format!("expected 16 keys, found {}", keys.len()))
+}
+
+fn keymap_to_text(keymap: &Keymap) -> String {
+    let names: Vec<String> = keymap.0.iter().map(|key| key_name(*key)).collect();
+    names.join(" ")
+}
+
+/// Order of the keys on the hex keypad, row by row.
+const KEYPAD: [usize; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+/// Settings window editing the keymap of the current ROM.
+#[derive(Default)]
+pub struct KeymapWindow {
+    pub open: bool,
+    /// CHIP-8 key waiting for a host key to be pressed.
+    rebinding: Option<usize>,
+    status: Option<String>,
+}
+
+impl KeymapWindow {
+    /// Shows the window, changes are saved to `path` right away.
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        keymaps: &mut Keymaps,
+        rom: Option<u64>,
+        path: Option<&Path>,
+    ) {
+        let mut open = self.open;
+        let mut changed = false;
+
+        egui::Window::new("Key mapping")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                if let Some(hash) = rom {
+                    let mut per_rom = keymaps.roms.contains_key(&hash);
+
+                    if ui.checkbox(&mut per_rom, "Specific to this ROM").changed() {
+                        if per_rom {
+                            keymaps.roms.insert(hash, keymaps.default);
+                        } else {
+                            keymaps.roms.remove(&hash);
+                        }
+                        changed = true;
+                    }
+                }
+
+                let keymap = keymaps.get_mut(rom);
+
+                ui.horizontal(|ui| {
+                    for (name, preset) in [("QWERTY", QWERTY), ("AZERTY", AZERTY)] {
+                        if ui.button(name).clicked() {
+                            *keymap = preset;
+                            changed = true;
+                        }
+                    }
+                });
+
+                egui::Grid::new("keypad").show(ui, |ui| {
+                    for (n, key) in KEYPAD.iter().enumerate() {
+                        if n != 0 && n % 4 == 0 {
+                            ui.end_row();
+                        }
+
+                        let text = if self.rebinding == Some(*key) {
+                            format!("{:X}: ...", key)
+                        } else {
+                            format!("{:X}: {}", key, key_name(keymap.0[*key]))
+                        };
+
+                        if ui.button(text).clicked() {
+                            self.rebinding = Some(*key);
+                        }
+                    }
+                });
+
+                if let Some(key) = self.rebinding {
+                    ui.label("Press a host key, Escape to cancel");
+
+                    let pressed = ctx.input().events.iter().find_map(|event| match event {
+                        Event::Key {
+                            key,
+                            pressed: true,
+                            modifiers,
+                        } => Some((*key, modifiers.command)),
+                        _ => None,
+                    });
+
+                    match pressed {
+                        Some((Key::Escape, _)) => self.rebinding = None,
+                        // Ctrl+1-4 save states, the game never sees keys
+                        // held with Ctrl
+                        Some((host_key, true)) => {
+                            self.status = Some(format!(
+                                "Ctrl+{} is a hotkey, press the key alone",
+                                key_name(host_key)
+                            ));
+                        }
+                        Some((host_key, false)) => match reserved(host_key) {
+                            Some(usage) => {
+                                self.status = Some(format!(
+                                    "{} is reserved for {}",
+                                    key_name(host_key),
+                                    usage
+                                ));
+                            }
+                            None => {
+                                keymap.0[key] = host_key;
+                                self.rebinding = None;
+                                changed = true;
+                            }
+                        },
+                        None => {}
+                    }
+                }
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+
+        if changed {
+            self.status = match path {
+                Some(path) => keymaps
+                    .save(path)
+                    .err()
+                    .map(|e| format!("Could not save {}: {}", path.display(), e)),
+                None => Some("No config directory, the mapping will not be saved".to_string()),
+            };
+        }
+
+        self.open = open;
+    }
+
+    pub fn is_rebinding(&self) -> bool {
+        self.rebinding.is_some()
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gui::keymap::{Keymaps, AZERTY, QWERTY};
+
+    #[test]
+    fn test_keymaps_roundtrip() {
+        let mut keymaps = Keymaps::default();
+        keymaps.roms.insert(0x1234, AZERTY);
+
+        let parsed = Keymaps::parse(&keymaps.to_text()).unwrap();
+
+        assert!(parsed.default == QWERTY);
+        assert!(*parsed.get(Some(0x1234)) == AZERTY);
+        assert!(*parsed.get(Some(0x5678)) == QWERTY);
+    }
+
+    #[test]
+    fn test_keymaps_parse_errors() {
+        assert!(Keymaps::parse("default X Num1").is_err());
+        assert!(Keymaps::parse("rom zz X").is_err());
+        assert!(Keymaps::parse("default X X X X X X X X X X X X X X X F13").is_err());
+        assert!(Keymaps::parse("default X X X X X X X X X X X X X X X Backspace").is_err());
+    }
+}
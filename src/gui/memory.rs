@@ -0,0 +1,204 @@
+use eframe::egui::{self, Context, Label, RichText, Sense, TextEdit, TextStyle};
+use eframe::epaint::Color32;
+
+use chipr::Chip8;
+
+/// Background of the bytes written recently.
+const WRITTEN_COLOR: Color32 = Color32::from_rgb(0x80, 0x50, 0x10);
+/// Background of the bytes from `I` on.
+const I_COLOR: Color32 = Color32::from_rgb(0x30, 0x50, 0x80);
+/// Bytes highlighted from `I`, as many as `Fx55`/`Fx65` can reach.
+const I_LEN: usize = 16;
+
+/// A value which can be edited in place.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Byte(usize),
+    V(usize),
+    I,
+    Pc,
+    Dt,
+    St,
+}
+
+impl Target {
+    fn get(self, chip8: &Chip8) -> u16 {
+        match self {
+            Target::Byte(addr) => chip8.mem.ram[addr] as u16,
+            Target::V(x) => chip8.v[x] as u16,
+            Target::I => chip8.i,
+            Target::Pc => chip8.pc,
+            Target::Dt => chip8.delay_timer as u16,
+            Target::St => chip8.sound_timer as u16,
+        }
+    }
+
+    fn set(self, chip8: &mut Chip8, value: u16) {
+        match self {
+            Target::Byte(addr) => chip8.mem.ram[addr] = value as u8,
+            Target::V(x) => chip8.v[x] = value as u8,
+            Target::I => chip8.i = value,
+            Target::Pc => chip8.pc = value,
+            Target::Dt => chip8.delay_timer = value as u8,
+            Target::St => chip8.sound_timer = value as u8,
+        }
+    }
+
+    fn digits(self) -> usize {
+        match self {
+            Target::I | Target::Pc => 4,
+            _ => 2,
+        }
+    }
+}
+
+/// Hex view of the memory and the registers, editable while paused.
+#[derive(Default)]
+pub struct MemoryEditor {
+    pub open: bool,
+    /// Disables editing even while paused.
+    pub locked: bool,
+    editing: Option<(Target, String)>,
+    /// Set when editing starts, to focus the text field once.
+    focus: bool,
+}
+
+impl MemoryEditor {
+    /// Shows the memory window, bytes written in the last `recent` cycles
+    /// are highlighted.
+    pub fn show(&mut self, ctx: &Context, chip8: &mut Chip8, recent: u64) {
+        let mut open = self.open;
+
+        egui::Window::new("Memory").open(&mut open).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.colored_label(WRITTEN_COLOR, "Written recently");
+                ui.colored_label(I_COLOR, "From I");
+            });
+
+            let row_height = ui.text_style_height(&TextStyle::Monospace);
+            let rows = chip8.mem.ram.len() / 16;
+            let i = chip8.i as usize;
+
+            // Only the visible rows are laid out, XO-CHIP has 64 KiB
+            egui::ScrollArea::vertical().show_rows(ui, row_height, rows, |ui, rows| {
+                for row in rows {
+                    ui.horizontal(|ui| {
+                        ui.spacing_mut().item_spacing.x = 4.0;
+                        ui.label(RichText::new(format!("{:04X}", row * 16)).monospace());
+
+                        for addr in row * 16..row * 16 + 16 {
+                            let written = chip8.mem.written_at[addr];
+                            let background = if written != 0 && chip8.cycles - written < recent {
+                                WRITTEN_COLOR
+                            } else if (i..i + I_LEN).contains(&addr) {
+                                I_COLOR
+                            } else {
+                                Color32::TRANSPARENT
+                            };
+
+                            self.cell(ui, chip8, Target::Byte(addr), background);
+                        }
+
+                        let ascii: String = chip8.mem.ram[row * 16..row * 16 + 16]
+                            .iter()
+                            .map(|&byte| match byte {
+                                0x20..=0x7E => byte as char,
+                                _ => '.',
+                            })
+                            .collect();
+                        ui.label(RichText::new(ascii).monospace().weak());
+                    });
+                }
+            });
+        });
+
+        self.open = open;
+    }
+
+    /// Shows the registers.
+    pub fn registers(&mut self, ui: &mut egui::Ui, chip8: &mut Chip8) {
+        ui.horizontal(|ui| {
+            for (name, target) in [
+                ("I", Target::I),
+                ("PC", Target::Pc),
+                ("DT", Target::Dt),
+                ("ST", Target::St),
+            ] {
+                ui.label(format!("{} =", name));
+                self.cell(ui, chip8, target, Color32::TRANSPARENT);
+            }
+
+            if let Some(x) = chip8.target_register {
+                ui.label(format!("Waiting for a key in V{:X}", x));
+            }
+        });
+
+        egui::Grid::new("v_regs").striped(true).show(ui, |ui| {
+            for x in 0..16 {
+                if x != 0 && x % 4 == 0 {
+                    ui.end_row();
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label(format!("V{:X} =", x));
+                    self.cell(ui, chip8, Target::V(x), Color32::TRANSPARENT);
+                });
+            }
+        });
+
+        if self.editable(chip8) {
+            ui.label("Click a value to edit it");
+        }
+    }
+
+    fn editable(&self, chip8: &Chip8) -> bool {
+        chip8.paused && !self.locked
+    }
+
+    /// A value in hex, which turns into a text field when clicked while the
+    /// machine is paused. The new value is written once the field loses
+    /// focus, by pressing Enter or clicking elsewhere.
+    fn cell(&mut self, ui: &mut egui::Ui, chip8: &mut Chip8, target: Target, background: Color32) {
+        let digits = target.digits();
+        let editable = self.editable(chip8);
+
+        if let Some((_, text)) = self.editing.as_mut().filter(|(t, _)| *t == target) {
+            let width = digits as f32
+                * ui.fonts()
+                    .glyph_width(&TextStyle::Monospace.resolve(ui.style()), '0');
+            let response = ui.add(
+                TextEdit::singleline(text)
+                    .font(TextStyle::Monospace)
+                    .desired_width(width)
+                    .frame(false),
+            );
+
+            if self.focus {
+                response.request_focus();
+                self.focus = false;
+            }
+
+            if response.lost_focus() {
+                let max = if digits == 4 { 0xFFFF } else { 0xFF };
+
+                match u16::from_str_radix(text.trim(), 16) {
+                    Ok(value) if value <= max && editable => target.set(chip8, value),
+                    _ => {}
+                }
+                self.editing = None;
+            }
+
+            return;
+        }
+
+        let value = format!("{:0width$X}", target.get(chip8), width = digits);
+        let text = RichText::new(&value)
+            .monospace()
+            .background_color(background);
+
+        if ui.add(Label::new(text).sense(Sense::click())).clicked() && editable {
+            self.editing = Some((target, value));
+            self.focus = true;
+        }
+    }
+}
@@ -0,0 +1,850 @@
+use eframe::egui::{Context, Sense, TextStyle, Visuals};
+use eframe::epaint::{Color32, Rect, Rounding, Vec2};
+use eframe::{egui, App, Frame, NativeOptions};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use chipr::audio::Waveform;
+use chipr::breakpoint::Hit;
+use chipr::chip8::STACK_SIZE;
+use chipr::clock::TIMER_HZ;
+use chipr::hash::fnv1a;
+use chipr::instr::bytes_to_word;
+use chipr::movie::{Movie, Playback};
+use chipr::trace::Tracer;
+use chipr::{
+    AddressMode, Breakpoint, Chip8, Clock, ExecError, Font, IndexIncrement, Quirks, Rewind,
+    StateError, Variant, HEIGHT, WIDTH,
+};
+
+use audio::Audio;
+use breakpoints::BreakpointsEditor;
+use disasm::DisasmView;
+use keymap::{KeymapWindow, Keymaps};
+use memory::MemoryEditor;
+use sprites::SpriteViewer;
+use states::{Action, SaveSlots};
+
+mod audio;
+mod breakpoints;
+mod config;
+mod disasm;
+mod keymap;
+mod memory;
+mod sprites;
+mod states;
+
+/// Colors of the pixels by lit planes, the background is left undrawn.
+const PALETTE: [Color32; 4] = [
+    Color32::TRANSPARENT,
+    Color32::WHITE,
+    Color32::from_rgb(0xFF, 0xAA, 0x00),
+    Color32::from_rgb(0x88, 0x88, 0x88),
+];
+
+pub fn main() -> ! {
+    eframe::run_native(
+        "Chipr",
+        NativeOptions::default(),
+        Box::new(|cc| Box::new(Chip8Emu::new(cc))),
+    );
+}
+
+struct Chip8Emu {
+    rom_path: Option<PathBuf>,
+    rom_hash: Option<u64>,
+    chip8: Option<Chip8>,
+    error: Option<ExecError>,
+    load_error: Option<String>,
+    address_mode: AddressMode,
+    quirks: Quirks,
+    variant: Variant,
+    font: Font,
+    stack_depth: usize,
+    clock: Clock,
+    last_update: Instant,
+    keymaps: Keymaps,
+    keymaps_path: Option<PathBuf>,
+    keymap_window: KeymapWindow,
+    audio: Result<Audio, String>,
+    save_slots: SaveSlots,
+    rewind: Rewind,
+    rewind_seconds: usize,
+    rewind_status: Option<String>,
+    disasm_view: DisasmView,
+    breakpoints: Vec<Breakpoint>,
+    breakpoints_editor: BreakpointsEditor,
+    /// Breakpoint the machine stopped at.
+    hit: Option<Hit>,
+    memory_editor: MemoryEditor,
+    sprite_viewer: SpriteViewer,
+    /// Whether the last instructions are traced, to look back from an error.
+    keep_history: bool,
+    /// Where the movie being recorded is saved.
+    movie_path: Option<PathBuf>,
+    movie_status: Option<String>,
+}
+
+/// Seconds of play kept for rewinding by default.
+const REWIND_SECONDS: usize = 10;
+
+impl Chip8Emu {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        cc.egui_ctx.set_visuals(Visuals::dark());
+
+        let keymaps_path = config::config_dir().map(|dir| dir.join("keymap.cfg"));
+        let mut keymap_window = KeymapWindow::default();
+        let keymaps = match &keymaps_path {
+            Some(path) => Keymaps::load(path).unwrap_or_else(|e| {
+                keymap_window.set_status(format!("Could not load {}: {}", path.display(), e));
+                Keymaps::default()
+            }),
+            None => Keymaps::default(),
+        };
+
+        Self {
+            rom_path: None,
+            rom_hash: None,
+            chip8: None,
+            error: None,
+            load_error: None,
+            address_mode: AddressMode::Fault,
+            quirks: Quirks::default(),
+            variant: Variant::default(),
+            font: Font::default(),
+            stack_depth: STACK_SIZE,
+            clock: Clock::default(),
+            last_update: Instant::now(),
+            keymaps,
+            keymaps_path,
+            keymap_window,
+            audio: Audio::new(),
+            save_slots: SaveSlots::new(config::config_dir().map(|dir| dir.join("states"))),
+            rewind: Rewind::new(REWIND_SECONDS * TIMER_HZ as usize),
+            rewind_seconds: REWIND_SECONDS,
+            rewind_status: None,
+            disasm_view: DisasmView::default(),
+            breakpoints: Vec::new(),
+            breakpoints_editor: BreakpointsEditor::default(),
+            hit: None,
+            memory_editor: MemoryEditor::default(),
+            sprite_viewer: SpriteViewer::default(),
+            keep_history: false,
+            movie_path: None,
+            movie_status: None,
+        }
+    }
+
+    fn start_chip8(&mut self) {
+        let path = self.rom_path.clone().unwrap();
+
+        self.stop_movie();
+        self.chip8 = None;
+        self.rom_hash = None;
+        self.hit = None;
+        self.rewind.clear();
+        self.disasm_view.reset();
+        self.error = None;
+        self.load_error = None;
+
+        let rom = match fs::read(&path) {
+            Ok(rom) => rom,
+            Err(e) => {
+                self.load_error = Some(format!("{}: {}", path.display(), e));
+                return;
+            }
+        };
+
+        match Chip8::from_rom_with_variant(&rom, self.variant) {
+            Ok(mut chip8) => {
+                chip8.mem.address_mode = self.address_mode;
+                chip8.quirks = self.quirks;
+                chip8.mem.set_font(self.font);
+                chip8.stack_depth = self.stack_depth;
+                self.chip8 = Some(chip8);
+                self.rom_hash = Some(fnv1a(&rom));
+            }
+            Err(e) => self.load_error = Some(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// Replaces the machine with a restored one, the settings follow it.
+    ///
+    /// States of another ROM are refused, the ROM file and the per-ROM
+    /// settings would not match them.
+    fn restore(&mut self, chip8: Chip8) -> Result<(), String> {
+        if let (Some(current), Some(hash)) = (self.rom_hash, chip8.rom_hash) {
+            if hash != current {
+                return Err(format!("the state is of another ROM ({:016X})", hash));
+            }
+        }
+
+        self.stop_movie();
+        self.rom_hash = self.rom_hash.or(chip8.rom_hash);
+        if let Some(font) = chip8.mem.font() {
+            self.font = font;
+        }
+        self.address_mode = chip8.mem.address_mode;
+        self.quirks = chip8.quirks;
+        self.variant = chip8.variant;
+        self.stack_depth = chip8.stack_depth;
+        self.error = None;
+        self.hit = None;
+        self.rewind.clear();
+        self.disasm_view.reset();
+        self.chip8 = Some(chip8);
+
+        Ok(())
+    }
+
+    /// Reports a state of the rewind buffer which does not load, and drops
+    /// the buffer.
+    fn rewind_error(&mut self, error: StateError) {
+        self.rewind_status = Some(format!("Could not rewind: {}", error));
+        self.rewind.clear();
+    }
+
+    /// Restarts the ROM with a new random seed and records a movie of it to
+    /// `path`.
+    fn record_movie(&mut self, path: PathBuf) {
+        if self.rom_path.is_none() {
+            self.movie_status = Some("Open a ROM first".to_string());
+            return;
+        }
+        self.start_chip8();
+
+        if let (Some(chip8), Some(hash)) = (&mut self.chip8, self.rom_hash) {
+            chip8.paused = false;
+
+            let movie = Movie::new(hash, chip8, self.font, self.clock.cpu_hz);
+            self.clock.reset();
+            self.clock.playback = Some(Playback::record(movie));
+            self.movie_path = Some(path);
+            self.movie_status = None;
+        }
+    }
+
+    /// Restarts the ROM with the settings of the movie at `path` and replays
+    /// it.
+    fn replay_movie(&mut self, path: &Path) {
+        let movie = match Movie::load(path) {
+            Ok(movie) => movie,
+            Err(e) => {
+                self.movie_status = Some(format!("Could not load {}: {}", path.display(), e));
+                return;
+            }
+        };
+
+        let rom_path = match self.rom_path.clone() {
+            Some(path) => path,
+            None => {
+                self.movie_status = Some("Open the ROM of the movie first".to_string());
+                return;
+            }
+        };
+        let chip8 = fs::read(&rom_path)
+            .map_err(|e| format!("{}: {}", rom_path.display(), e))
+            .and_then(|rom| movie.start(&rom));
+
+        match chip8 {
+            Ok(chip8) => {
+                // The machine is of the same ROM, restoring it cannot fail
+                self.restore(chip8).unwrap();
+                self.font = movie.font;
+                self.clock.cpu_hz = movie.cpu_hz;
+                self.clock.reset();
+                self.clock.playback = Some(Playback::replay(movie));
+                self.movie_status = None;
+            }
+            Err(e) => self.movie_status = Some(format!("Could not replay the movie: {}", e)),
+        }
+    }
+
+    /// Ends the movie being recorded or replayed, if any. A recording is
+    /// saved.
+    fn stop_movie(&mut self) {
+        match self.clock.playback.take() {
+            Some(playback @ Playback::Recording { .. }) => {
+                let path = self.movie_path.take().unwrap();
+                let movie = playback.into_movie();
+
+                self.movie_status = Some(match movie.save(&path) {
+                    Ok(()) => format!("Saved {} frames to {}", movie.frames.len(), path.display()),
+                    Err(e) => format!("Could not save {}: {}", path.display(), e),
+                });
+            }
+            Some(playback) => {
+                self.movie_status = Some(if playback.finished() {
+                    "Replay finished".to_string()
+                } else {
+                    "Replay stopped".to_string()
+                });
+            }
+            None => {}
+        }
+    }
+
+    fn save_slot_action(&mut self, ctx: &Context, action: Action) {
+        match action {
+            Action::Save(n) => {
+                if let Some(chip8) = &self.chip8 {
+                    self.save_slots.save(ctx, n, chip8);
+                }
+            }
+            Action::Load(n) => {
+                if let Some(chip8) = self.save_slots.load(n) {
+                    if let Err(e) = self.restore(chip8) {
+                        self.save_slots.status =
+                            Some(format!("Could not load slot {}: {}", n + 1, e));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl App for Chip8Emu {
+    fn update(&mut self, ctx: &Context, frame: &mut Frame) {
+        if ctx.input().key_pressed(egui::Key::Escape) && !self.keymap_window.is_rebinding() {
+            frame.quit();
+        }
+
+        let now = Instant::now();
+        let elapsed = now - self.last_update;
+        self.last_update = now;
+
+        self.keymap_window.show(
+            ctx,
+            &mut self.keymaps,
+            self.rom_hash,
+            self.keymaps_path.as_deref(),
+        );
+
+        if let Some(hash) = self.rom_hash {
+            self.save_slots.set_rom(ctx, hash);
+
+            if let Some(action) = SaveSlots::hotkey(ctx) {
+                self.save_slot_action(ctx, action);
+            }
+        }
+
+        // Backspace plays the recorded frames backwards while held
+        let rewinding = ctx.input().key_down(egui::Key::Backspace)
+            && !self.keymap_window.is_rebinding()
+            && !ctx.wants_keyboard_input()
+            && self.chip8.is_some();
+
+        let mut movie_over = false;
+        if rewinding {
+            self.stop_movie();
+
+            // One state per frame, as fast as the game runs forward
+            for _ in 0..self.clock.take_frames(elapsed) {
+                match self.rewind.pop() {
+                    Ok(Some(mut chip8)) => {
+                        if let Some(current) = &mut self.chip8 {
+                            chip8.carry_over(current);
+                        }
+                        self.error = None;
+                        self.chip8 = Some(chip8);
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        self.rewind_error(e);
+                        break;
+                    }
+                }
+            }
+            ctx.request_repaint();
+        } else if let Some(chip8) = &mut self.chip8 {
+            // Keys held with Ctrl are save state hotkeys, typing in a text
+            // field is not for the game either
+            let typing = ctx.wants_keyboard_input();
+            let input = ctx.input();
+            let keymap = self.keymaps.get(self.rom_hash);
+            let ignored = self.keymap_window.is_rebinding() || input.modifiers.command || typing;
+
+            let mut keys = [false; 16];
+            for (key, host_key) in keymap.0.iter().enumerate() {
+                keys[key] = !ignored && input.key_down(*host_key);
+            }
+            drop(input);
+
+            // A movie hands the keys to the machine at the start of frames
+            match &mut self.clock.playback {
+                Some(playback) => playback.hold(keys),
+                None => chip8.set_keys(keys),
+            }
+
+            // States for rewinding are taken as frames start, with their keys
+            let rewind = &mut self.rewind;
+            match self
+                .clock
+                .advance_with(chip8, elapsed, |chip8| rewind.push(chip8))
+            {
+                Ok(Some(hit)) => self.hit = Some(hit),
+                Ok(None) => {}
+                Err(error) => self.error = Some(error),
+            }
+
+            // Pausing, at a breakpoint or on an error too, ends movies
+            movie_over = self
+                .clock
+                .playback
+                .as_ref()
+                .is_some_and(|playback| playback.finished() || chip8.paused);
+
+            // Keep frames coming while the game runs, egui only repaints on
+            // input otherwise
+            if !chip8.paused {
+                ctx.request_repaint();
+            }
+        }
+
+        if movie_over {
+            self.stop_movie();
+        }
+
+        if let Ok(audio) = &self.audio {
+            let active = self
+                .chip8
+                .as_ref()
+                .is_some_and(|chip8| !chip8.paused && chip8.buzzer_active());
+
+            if let Some(chip8) = &self.chip8 {
+                let mut beeper = audio.beeper.lock().unwrap();
+                beeper.pattern = chip8.pattern;
+                beeper.pitch = chip8.pitch;
+            }
+
+            audio.set_active(active);
+        }
+
+        egui::SidePanel::left("load_panel").show(ctx, |ui| {
+            if ui.button("Open ROM").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.rom_path = Some(path);
+                    self.start_chip8();
+                }
+            }
+
+            if let Some(error) = &self.load_error {
+                ui.colored_label(Color32::RED, error);
+            }
+
+            if ui.button("Key mapping").clicked() {
+                self.keymap_window.open = true;
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Memory").clicked() {
+                    self.memory_editor.open = true;
+                }
+                if ui.button("Sprites").clicked() {
+                    self.sprite_viewer.open = true;
+                }
+            });
+
+            // The settings of a movie are fixed while it plays
+            ui.add_enabled_ui(self.clock.playback.is_none(), |ui| {
+                egui::ComboBox::from_label("Memory")
+                    .selected_text(match self.address_mode {
+                        AddressMode::Wrap => "Wrap",
+                        AddressMode::Fault => "Fault",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.address_mode, AddressMode::Wrap, "Wrap");
+                        ui.selectable_value(&mut self.address_mode, AddressMode::Fault, "Fault");
+                    });
+
+                let variant = self.variant;
+                egui::ComboBox::from_label("Variant")
+                    .selected_text(self.variant.name())
+                    .show_ui(ui, |ui| {
+                        for (name, variant) in Variant::ALL {
+                            ui.selectable_value(&mut self.variant, variant, name);
+                        }
+                    });
+
+                // The memory size depends on the variant, so the ROM starts over
+                if self.variant != variant && self.rom_path.is_some() {
+                    self.start_chip8();
+                }
+
+                let font = self.font;
+                egui::ComboBox::from_label("Font")
+                    .selected_text(self.font.name())
+                    .show_ui(ui, |ui| {
+                        for (name, font) in Font::ALL {
+                            ui.selectable_value(&mut self.font, font, name);
+                        }
+                    });
+
+                if self.font != font {
+                    if let Some(chip8) = &mut self.chip8 {
+                        chip8.mem.set_font(self.font);
+                    }
+                }
+
+                ui.collapsing("Quirks", |ui| {
+                    let preset = Quirks::PRESETS
+                        .iter()
+                        .find(|(_, quirks)| *quirks == self.quirks)
+                        .map_or("Custom", |(name, _)| *name);
+
+                    egui::ComboBox::from_label("Preset")
+                        .selected_text(preset)
+                        .show_ui(ui, |ui| {
+                            for (name, quirks) in Quirks::PRESETS {
+                                ui.selectable_value(&mut self.quirks, quirks, name);
+                            }
+                        });
+
+                    let quirks = &mut self.quirks;
+                    ui.checkbox(&mut quirks.shift_uses_vy, "Shifts use Vy");
+                    egui::ComboBox::from_label("Fx55/Fx65 increment I by")
+                        .selected_text(quirks.load_store_increments_i.name())
+                        .show_ui(ui, |ui| {
+                            for (name, increment) in IndexIncrement::ALL {
+                                let value = &mut quirks.load_store_increments_i;
+                                ui.selectable_value(value, increment, name);
+                            }
+                        });
+                    ui.checkbox(&mut quirks.jump_uses_vx, "Bnnn jumps with Vx");
+                    ui.checkbox(&mut quirks.vf_reset, "Logic ops reset VF");
+                    ui.checkbox(&mut quirks.clip_sprites, "Clip sprites");
+                    ui.checkbox(&mut quirks.display_wait, "Wait for display");
+                });
+
+                ui.add(egui::Slider::new(&mut self.stack_depth, 1..=64).text("Stack depth"));
+                ui.checkbox(&mut self.keep_history, "Keep last instructions");
+
+                if let Some(chip8) = &mut self.chip8 {
+                    chip8.mem.address_mode = self.address_mode;
+                    chip8.quirks = self.quirks;
+                    chip8.stack_depth = self.stack_depth;
+                    chip8.breakpoints = self.breakpoints.clone();
+                    if !self.keep_history {
+                        chip8.trace = None;
+                    } else if chip8.trace.is_none() {
+                        chip8.trace = Some(Tracer::new());
+                    }
+                }
+
+                ui.add(
+                    egui::Slider::new(&mut self.clock.cpu_hz, 60..=5000)
+                        .logarithmic(true)
+                        .text("Hz"),
+                );
+                ui.label(format!(
+                    "{:.1} instructions per frame",
+                    self.clock.cpu_hz as f32 / TIMER_HZ as f32
+                ));
+            });
+
+            ui.collapsing("Sound", |ui| match &self.audio {
+                Ok(audio) => {
+                    let mut beeper = audio.beeper.lock().unwrap();
+
+                    ui.checkbox(&mut beeper.muted, "Mute");
+                    ui.add(egui::Slider::new(&mut beeper.volume, 0.0..=1.0).text("Volume"));
+                    ui.add(
+                        egui::Slider::new(&mut beeper.frequency, 50.0..=2000.0)
+                            .logarithmic(true)
+                            .text("Hz"),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.radio_value(&mut beeper.waveform, Waveform::Square, "Square");
+                        ui.radio_value(&mut beeper.waveform, Waveform::Sine, "Sine");
+                    });
+                }
+                Err(e) => {
+                    ui.label(format!("No sound: {}", e));
+                }
+            });
+
+            if self.chip8.is_none() {
+                ui.set_enabled(false);
+            }
+
+            if ui.button("Start").clicked() {
+                if let Some(chip8) = &mut self.chip8 {
+                    chip8.paused = false;
+                    self.error = None;
+                    self.hit = None;
+                }
+            }
+
+            if ui.button("Stop").clicked() {
+                if let Some(chip8) = &mut self.chip8 {
+                    chip8.paused = true;
+                }
+            }
+
+            if ui.button("Reset ROM").clicked() {
+                self.start_chip8();
+            }
+
+            // Instructions run outside of frames would not replay
+            let movie_active = self.clock.playback.is_some();
+            ui.add_enabled_ui(!movie_active, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("Step 1").clicked() {
+                        if let Some(chip8) = &mut self.chip8 {
+                            self.rewind.push(chip8);
+                            self.error = chip8.execute().err();
+                        }
+                    }
+
+                    if ui
+                        .add_enabled(!self.rewind.is_empty(), egui::Button::new("Step back 1"))
+                        .clicked()
+                    {
+                        let stepped = self
+                            .chip8
+                            .as_ref()
+                            .map(|chip8| self.rewind.step_back(chip8));
+
+                        match stepped {
+                            Some(Ok(Some(mut chip8))) => {
+                                if let Some(current) = &mut self.chip8 {
+                                    chip8.carry_over(current);
+                                }
+                                self.error = None;
+                                self.chip8 = Some(chip8);
+                            }
+                            Some(Err(e)) => self.rewind_error(e),
+                            _ => {}
+                        }
+                    }
+                })
+            });
+
+            ui.collapsing("Rewind", |ui| {
+                ui.label("Hold Backspace to rewind");
+                ui.add(egui::Slider::new(&mut self.rewind_seconds, 0..=60).text("Seconds kept"));
+                self.rewind.capacity = self.rewind_seconds * TIMER_HZ as usize;
+
+                ui.label(format!(
+                    "{} states in {} KiB",
+                    self.rewind.len(),
+                    self.rewind.size_in_bytes() / 1024
+                ));
+
+                if let Some(status) = &self.rewind_status {
+                    ui.colored_label(Color32::RED, status);
+                }
+            });
+
+            let mut action = None;
+            ui.collapsing("Save states", |ui| {
+                ui.label("Ctrl+1-4 to save, Ctrl+Shift+1-4 to load");
+                action = self.save_slots.show(ui);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Save to file").clicked() {
+                        let path = rfd::FileDialog::new()
+                            .add_filter("Save state", &["state"])
+                            .save_file();
+
+                        if let (Some(path), Some(chip8)) = (path, &self.chip8) {
+                            self.save_slots.status =
+                                states::write_state(&path, &chip8.save_state())
+                                    .err()
+                                    .map(|e| format!("Could not save {}: {}", path.display(), e));
+                        }
+                    }
+
+                    if ui.button("Load from file").clicked() {
+                        let path = rfd::FileDialog::new()
+                            .add_filter("Save state", &["state"])
+                            .pick_file();
+
+                        if let Some(path) = path {
+                            let restored = fs::read(&path)
+                                .map_err(|e| e.to_string())
+                                .and_then(|state| {
+                                    Chip8::from_state(&state).map_err(|e| e.to_string())
+                                })
+                                .and_then(|chip8| self.restore(chip8));
+
+                            if let Err(e) = restored {
+                                self.save_slots.status =
+                                    Some(format!("Could not load {}: {}", path.display(), e));
+                            }
+                        }
+                    }
+                });
+            });
+
+            if let Some(action) = action {
+                self.save_slot_action(ctx, action);
+            }
+
+            ui.collapsing("Movie", |ui| {
+                match &self.clock.playback {
+                    Some(Playback::Recording { movie, .. }) => {
+                        ui.label(format!("Recording frame {}", movie.frames.len()));
+                    }
+                    Some(Playback::Replaying { movie, frame }) => {
+                        ui.label(format!("Replaying frame {}/{}", frame, movie.frames.len()));
+                    }
+                    None => {}
+                }
+
+                ui.horizontal(|ui| {
+                    if self.clock.playback.is_some() {
+                        if ui.button("Stop movie").clicked() {
+                            self.stop_movie();
+                        }
+                        return;
+                    }
+
+                    if ui.button("Record").clicked() {
+                        let path = rfd::FileDialog::new()
+                            .add_filter("Movie", &["movie"])
+                            .save_file();
+
+                        if let Some(path) = path {
+                            self.record_movie(path);
+                        }
+                    }
+
+                    if ui.button("Replay").clicked() {
+                        let path = rfd::FileDialog::new()
+                            .add_filter("Movie", &["movie"])
+                            .pick_file();
+
+                        if let Some(path) = path {
+                            self.replay_movie(&path);
+                        }
+                    }
+                });
+
+                if let Some(status) = &self.movie_status {
+                    ui.label(status);
+                }
+            });
+        });
+
+        // Edits made during a movie would not replay
+        self.memory_editor.locked = self.clock.playback.is_some();
+
+        if let Some(chip8) = &mut self.chip8 {
+            // Highlights the writes of about the last second
+            let recent = self.clock.cpu_hz as u64;
+            self.memory_editor.show(ctx, chip8, recent);
+            self.sprite_viewer.show(ctx, chip8);
+        }
+
+        egui::SidePanel::right("instructions").show(ctx, |ui| {
+            if let Some(chip8) = &self.chip8 {
+                // Clicking a line toggles a breakpoint on it
+                if let Some(addr) = self.disasm_view.show(ui, chip8) {
+                    let addr = addr as u16;
+                    let count = self.breakpoints.len();
+
+                    self.breakpoints.retain(|breakpoint| {
+                        !matches!(*breakpoint, Breakpoint::Address { addr: a, .. } if a == addr)
+                    });
+                    if self.breakpoints.len() == count {
+                        self.breakpoints.push(Breakpoint::Address {
+                            addr,
+                            condition: None,
+                        });
+                    }
+                }
+            }
+        });
+
+        egui::TopBottomPanel::bottom("debug_panel").show(ctx, |ui| {
+            if let Some(chip8) = &mut self.chip8 {
+                if let Some(error) = &self.error {
+                    ui.colored_label(Color32::RED, format!("Halted: {}", error));
+
+                    if let Some(tracer) = &chip8.trace {
+                        let title = format!("Last instructions ({})", tracer.recent.len());
+                        ui.collapsing(title, |ui| {
+                            let row_height = ui.text_style_height(&TextStyle::Monospace);
+
+                            // Newest first, the one before the error at the top
+                            egui::ScrollArea::both().max_height(200.0).show_rows(
+                                ui,
+                                row_height,
+                                tracer.recent.len(),
+                                |ui, rows| {
+                                    for row in rows {
+                                        let entry = &tracer.recent[tracer.recent.len() - 1 - row];
+                                        ui.monospace(entry.to_string());
+                                    }
+                                },
+                            );
+                        });
+                    }
+                }
+
+                if let Some(hit) = &self.hit {
+                    ui.colored_label(Color32::YELLOW, format!("Stopped at breakpoint: {}", hit));
+                }
+
+                self.memory_editor.registers(ui, chip8);
+
+                ui.collapsing("Breakpoints", |ui| {
+                    self.breakpoints_editor.show(ui, &mut self.breakpoints);
+                });
+
+                let title = format!("Call stack ({}/{})", chip8.stack.len(), chip8.stack_depth);
+                ui.collapsing(title, |ui| {
+                    // Innermost call first, each return address follows its 2nnn
+                    for &ret in chip8.stack.iter().rev() {
+                        let caller = ret.wrapping_sub(2);
+                        let target =
+                            match (chip8.mem.get(caller), chip8.mem.get(ret.wrapping_sub(1))) {
+                                (Ok(h), Ok(l)) => format!("{:#05X}", bytes_to_word(h, l) & 0xFFF),
+                                _ => "?".to_string(),
+                            };
+
+                        ui.label(format!(
+                            "{:#05X}: CALL {}, returns to {:#05X}",
+                            caller, target, ret
+                        ));
+                    }
+                });
+            }
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // Draw the game
+            // TODO Probably not very efficient
+            let (response, painter) = ui.allocate_painter(
+                Vec2::new((WIDTH * 4) as f32, (HEIGHT * 4) as f32),
+                Sense::hover(),
+            );
+
+            if let Some(chip8) = &self.chip8 {
+                // Hi-res pixels are half the size, the screen keeps its size
+                let (width, height) = (chip8.width(), chip8.height());
+                let size = (WIDTH * 4 / width) as f32;
+
+                for y in 0..height {
+                    for x in 0..width {
+                        let pixel = chip8.gfx[y * width + x];
+
+                        if pixel != 0 {
+                            painter.rect_filled(
+                                Rect::from_min_size(
+                                    response.rect.left_top()
+                                        + Vec2::new(x as f32 * size, y as f32 * size),
+                                    Vec2::splat(size),
+                                ),
+                                Rounding::none(),
+                                PALETTE[pixel as usize & 3],
+                            );
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,192 @@
+use eframe::egui::{self, Context, Sense};
+use eframe::epaint::{Color32, Rect, Rounding, Vec2};
+
+use chipr::{Chip8, Variant};
+
+/// Size of a sprite pixel on screen.
+const PIXEL: f32 = 8.0;
+
+/// Memory drawn as `Dxyn` would draw it, to find and check sprites.
+pub struct SpriteViewer {
+    pub open: bool,
+    addr: usize,
+    rows: usize,
+    /// 16x16 SUPER-CHIP sprites, read as 32 bytes.
+    wide: bool,
+    /// Keeps `addr` on `I`.
+    follow_i: bool,
+}
+
+impl Default for SpriteViewer {
+    fn default() -> Self {
+        SpriteViewer {
+            open: false,
+            addr: 0,
+            rows: 15,
+            wide: false,
+            follow_i: true,
+        }
+    }
+}
+
+impl SpriteViewer {
+    pub fn show(&mut self, ctx: &Context, chip8: &Chip8) {
+        let mut open = self.open;
+
+        egui::Window::new("Sprites")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let last = chip8.mem.ram.len() - 1;
+
+                ui.checkbox(&mut self.follow_i, "Follow I");
+                if self.follow_i {
+                    self.addr = chip8.i as usize;
+                }
+
+                ui.add_enabled_ui(!self.follow_i, |ui| {
+                    // Scrubbing through memory, one byte at a time
+                    ui.add(egui::Slider::new(&mut self.addr, 0..=last).show_value(false));
+                    ui.horizontal(|ui| {
+                        ui.monospace(format!("{:04X}", self.addr));
+
+                        // Past the sprites of every selected plane
+                        let len = self.len() * chip8.planes.count_ones().max(1) as usize;
+
+                        if ui.button("Previous").clicked() {
+                            self.addr = self.addr.saturating_sub(len);
+                        }
+                        if ui.button("Next").clicked() {
+                            self.addr = (self.addr + len).min(last);
+                        }
+                    });
+                });
+
+                ui.horizontal(|ui| {
+                    ui.add_enabled(
+                        !self.wide,
+                        egui::Slider::new(&mut self.rows, 1..=15).text("Rows"),
+                    );
+                    ui.add_enabled(
+                        chip8.variant != Variant::Chip8,
+                        egui::Checkbox::new(&mut self.wide, "16x16"),
+                    );
+                });
+                self.wide &= chip8.variant != Variant::Chip8;
+
+                let (width, rows) = if self.wide { (16, 16) } else { (8, self.rows) };
+                sprites(ui, chip8, self.addr, width, rows, chip8.planes);
+
+                ui.separator();
+
+                match chip8.last_draw {
+                    Some(draw) => {
+                        ui.label(format!(
+                            "Last drawn at ({}, {}), {}x{} from {:#05X}, {}",
+                            draw.x,
+                            draw.y,
+                            draw.width,
+                            draw.rows,
+                            draw.addr,
+                            if draw.collision {
+                                "collided"
+                            } else {
+                                "no collision"
+                            }
+                        ));
+                        sprites(
+                            ui,
+                            chip8,
+                            draw.addr as usize,
+                            draw.width,
+                            draw.rows,
+                            draw.planes,
+                        );
+                    }
+                    None => {
+                        ui.label("Nothing drawn yet");
+                    }
+                }
+            });
+
+        self.open = open;
+    }
+
+    fn len(&self) -> usize {
+        if self.wide {
+            32
+        } else {
+            self.rows
+        }
+    }
+}
+
+/// Draws the sprites at `addr` for each of `planes`, as XO-CHIP reads them:
+/// the sprite of the second plane follows the one of the first plane.
+fn sprites(ui: &mut egui::Ui, chip8: &Chip8, addr: usize, width: usize, rows: usize, planes: u8) {
+    if chip8.variant != Variant::XoChip {
+        sprite(ui, chip8, addr, width, rows);
+        return;
+    }
+    if planes == 0 {
+        ui.label("No plane selected");
+        return;
+    }
+
+    let len = width / 8 * rows;
+    let selected = [1, 2].into_iter().filter(|p| planes & p != 0);
+
+    for (k, plane) in selected.enumerate() {
+        ui.label(format!("Plane {}", plane));
+        sprite(ui, chip8, addr + k * len, width, rows);
+    }
+}
+
+/// Draws the sprite at `addr` as it reads memory now.
+fn sprite(ui: &mut egui::Ui, chip8: &Chip8, addr: usize, width: usize, rows: usize) {
+    let bytes_per_row = width / 8;
+    let data = match chip8
+        .mem
+        .read_bytes(addr as u16, (rows * bytes_per_row) as u8)
+    {
+        Ok(data) => data,
+        Err(e) => {
+            ui.label(format!("Past the end of memory at {:#X}", e.address));
+            return;
+        }
+    };
+
+    ui.horizontal(|ui| {
+        let (response, painter) = ui.allocate_painter(
+            Vec2::new(width as f32 * PIXEL, 16.0 * PIXEL),
+            Sense::hover(),
+        );
+        painter.rect_filled(response.rect, Rounding::none(), Color32::BLACK);
+
+        for (y, row) in data.chunks(bytes_per_row).enumerate() {
+            for (b, byte) in row.iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (0x80 >> bit) != 0 {
+                        let x = b * 8 + bit;
+
+                        painter.rect_filled(
+                            Rect::from_min_size(
+                                response.rect.left_top()
+                                    + Vec2::new(x as f32 * PIXEL, y as f32 * PIXEL),
+                                Vec2::splat(PIXEL),
+                            ),
+                            Rounding::none(),
+                            Color32::WHITE,
+                        );
+                    }
+                }
+            }
+        }
+
+        let hex: Vec<String> = data
+            .chunks(bytes_per_row)
+            .map(|row| row.iter().map(|byte| format!("{:02X}", byte)).collect())
+            .collect();
+        ui.monospace(hex.join("\n"));
+    });
+}
@@ -0,0 +1,192 @@
+use eframe::egui::{self, ColorImage, Context, Event, Key, TextureHandle};
+use eframe::epaint::Color32;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chipr::Chip8;
+
+use super::PALETTE;
+
+pub const SLOTS: usize = 4;
+const SLOT_KEYS: [Key; SLOTS] = [Key::Num1, Key::Num2, Key::Num3, Key::Num4];
+
+/// What the user asked for this frame.
+pub enum Action {
+    Save(usize),
+    Load(usize),
+}
+
+struct Slot {
+    state: Vec<u8>,
+    thumbnail: TextureHandle,
+}
+
+/// Quick-save slots of the current ROM, kept in memory and in
+/// `<dir>/<rom hash>-<slot>.state` files.
+pub struct SaveSlots {
+    dir: Option<PathBuf>,
+    rom: Option<u64>,
+    slots: Vec<Option<Slot>>,
+    pub status: Option<String>,
+}
+
+impl SaveSlots {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        SaveSlots {
+            dir,
+            rom: None,
+            slots: (0..SLOTS).map(|_| None).collect(),
+            status: None,
+        }
+    }
+
+    /// Switches to the slots of another ROM, loading them from disk.
+    pub fn set_rom(&mut self, ctx: &Context, rom: u64) {
+        if self.rom == Some(rom) {
+            return;
+        }
+
+        self.rom = Some(rom);
+        self.status = None;
+
+        for n in 0..SLOTS {
+            self.slots[n] = self
+                .path(n)
+                .and_then(|path| fs::read(path).ok())
+                .and_then(|state| {
+                    let chip8 = Chip8::from_state(&state).ok()?;
+
+                    Some(Slot {
+                        thumbnail: thumbnail(ctx, n, &chip8),
+                        state,
+                    })
+                });
+        }
+    }
+
+    /// Ctrl+1 to Ctrl+4 save to a slot, with Shift they load from it.
+    pub fn hotkey(ctx: &Context) -> Option<Action> {
+        ctx.input().events.iter().find_map(|event| match event {
+            Event::Key {
+                key,
+                pressed: true,
+                modifiers,
+            } if modifiers.command => {
+                let n = SLOT_KEYS.iter().position(|k| k == key)?;
+
+                Some(if modifiers.shift {
+                    Action::Load(n)
+                } else {
+                    Action::Save(n)
+                })
+            }
+            _ => None,
+        })
+    }
+
+    pub fn save(&mut self, ctx: &Context, n: usize, chip8: &Chip8) {
+        let state = chip8.save_state();
+
+        self.status = match self.path(n) {
+            Some(path) => match write_state(&path, &state) {
+                Ok(()) => Some(format!("Saved slot {}", n + 1)),
+                Err(e) => Some(format!("Could not save {}: {}", path.display(), e)),
+            },
+            None => Some(format!("Saved slot {} until the app quits", n + 1)),
+        };
+
+        self.slots[n] = Some(Slot {
+            thumbnail: thumbnail(ctx, n, chip8),
+            state,
+        });
+    }
+
+    pub fn load(&mut self, n: usize) -> Option<Chip8> {
+        let slot = match &self.slots[n] {
+            Some(slot) => slot,
+            None => {
+                self.status = Some(format!("Slot {} is empty", n + 1));
+                return None;
+            }
+        };
+
+        match Chip8::from_state(&slot.state) {
+            Ok(chip8) => {
+                self.status = Some(format!("Loaded slot {}", n + 1));
+                Some(chip8)
+            }
+            Err(e) => {
+                self.status = Some(format!("Could not load slot {}: {}", n + 1, e));
+                None
+            }
+        }
+    }
+
+    /// Shows the slots with their thumbnails and buttons.
+    pub fn show(&self, ui: &mut egui::Ui) -> Option<Action> {
+        let mut action = None;
+
+        egui::Grid::new("save_slots").show(ui, |ui| {
+            for (n, slot) in self.slots.iter().enumerate() {
+                match slot {
+                    Some(slot) => {
+                        ui.image(slot.thumbnail.id(), egui::Vec2::new(64.0, 32.0));
+                    }
+                    None => {
+                        ui.label(format!("Slot {}: empty", n + 1));
+                    }
+                }
+
+                if ui.button("Save").clicked() {
+                    action = Some(Action::Save(n));
+                }
+                if ui
+                    .add_enabled(slot.is_some(), egui::Button::new("Load"))
+                    .clicked()
+                {
+                    action = Some(Action::Load(n));
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(status) = &self.status {
+            ui.label(status);
+        }
+
+        action
+    }
+
+    fn path(&self, n: usize) -> Option<PathBuf> {
+        let (dir, rom) = (self.dir.as_ref()?, self.rom?);
+
+        Some(dir.join(format!("{:016x}-{}.state", rom, n + 1)))
+    }
+}
+
+/// Writes a save state, creating its directory if needed.
+pub fn write_state(path: &Path, state: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, state)
+}
+
+/// The display of `chip8` at one texel per pixel.
+fn thumbnail(ctx: &Context, n: usize, chip8: &Chip8) -> TextureHandle {
+    let pixels = chip8
+        .framebuffer()
+        .iter()
+        .map(|&pixel| match pixel {
+            0 => Color32::BLACK,
+            _ => PALETTE[pixel as usize & 3],
+        })
+        .collect();
+    let image = ColorImage {
+        size: [chip8.width(), chip8.height()],
+        pixels,
+    };
+
+    ctx.load_texture(format!("slot-{}", n), image)
+}
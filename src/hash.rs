@@ -0,0 +1,20 @@
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a hash, used to identify ROMs.
+pub fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(FNV_OFFSET, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::fnv1a;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -1,5 +1,6 @@
-use crate::chip8::{Chip8, HEIGHT, WIDTH};
-use rand::prelude::*;
+use crate::chip8::{Chip8, Draw};
+use crate::error::Fault;
+use crate::variant::Variant;
 
 const ADDR_MASK: u16 = 0xFFF;
 
@@ -11,13 +12,13 @@ pub trait Instructions {
     fn cls(&mut self);
 
     // 00EE
-    fn ret(&mut self);
+    fn ret(&mut self) -> Result<(), Fault>;
 
     // 1nnn
     fn jp_addr(&mut self, opcode: u16);
 
     // 2nnn
-    fn call_addr(&mut self, opcode: u16);
+    fn call_addr(&mut self, opcode: u16) -> Result<(), Fault>;
 
     // 3xkk
     fn se_vx_kk(&mut self, opcode: u16);
@@ -74,7 +75,7 @@ pub trait Instructions {
     fn rnd_vx_kk(&mut self, opcode: u16);
 
     // Dxyn
-    fn drw_vx_vy_nibble(&mut self, opcode: u16);
+    fn drw_vx_vy_nibble(&mut self, opcode: u16) -> Result<(), Fault>;
 
     // Ex9E
     fn skp_vx(&mut self, opcode: u16);
@@ -101,13 +102,65 @@ pub trait Instructions {
     fn ld_f_vx(&mut self, opcode: u16);
 
     // Fx33
-    fn ld_b_vx(&mut self, opcode: u16);
+    fn ld_b_vx(&mut self, opcode: u16) -> Result<(), Fault>;
 
     // Fx55
-    fn ld_addri_vx(&mut self, opcode: u16);
+    fn ld_addri_vx(&mut self, opcode: u16) -> Result<(), Fault>;
 
     // Fx65
-    fn ld_vx_addri(&mut self, opcode: u16);
+    fn ld_vx_addri(&mut self, opcode: u16) -> Result<(), Fault>;
+
+    // SUPER-CHIP
+
+    // 00Cn
+    fn scd_nibble(&mut self, opcode: u16);
+
+    // 00FB
+    fn scr(&mut self);
+
+    // 00FC
+    fn scl(&mut self);
+
+    // 00FD
+    fn exit(&mut self);
+
+    // 00FE
+    fn low(&mut self);
+
+    // 00FF
+    fn high(&mut self);
+
+    // Fx30
+    fn ld_hf_vx(&mut self, opcode: u16);
+
+    // Fx75
+    fn ld_r_vx(&mut self, opcode: u16);
+
+    // Fx85
+    fn ld_vx_r(&mut self, opcode: u16);
+
+    // XO-CHIP
+
+    // 00Dn
+    fn scu_nibble(&mut self, opcode: u16);
+
+    // 5xy2
+    fn ld_addri_vx_vy(&mut self, opcode: u16) -> Result<(), Fault>;
+
+    // 5xy3
+    fn ld_vx_vy_addri(&mut self, opcode: u16) -> Result<(), Fault>;
+
+    // F000 nnnn
+    fn ld_i_long(&mut self) -> Result<(), Fault>;
+
+    // Fn01
+    fn plane_n(&mut self, opcode: u16);
+
+    // F002
+    fn audio(&mut self) -> Result<(), Fault>;
+
+    // Fx3A
+    fn pitch_vx(&mut self, opcode: u16);
 }
 
 impl Instructions for Chip8 {
@@ -118,14 +171,18 @@ impl Instructions for Chip8 {
 
     // 00E0
     fn cls(&mut self) {
-        self.gfx = [false; HEIGHT * WIDTH];
+        let planes = self.planes;
+
+        for pixel in self.gfx.iter_mut() {
+            *pixel &= !planes;
+        }
     }
 
     // 00EE
-    fn ret(&mut self) {
-        if let Some(value) = self.stack.pop() {
-            self.pc = value;
-        }
+    fn ret(&mut self) -> Result<(), Fault> {
+        self.pc = self.stack.pop().ok_or(Fault::StackUnderflow)?;
+
+        Ok(())
     }
 
     // 1nnn
@@ -135,11 +192,17 @@ impl Instructions for Chip8 {
     }
 
     // 2nnn
-    fn call_addr(&mut self, opcode: u16) {
+    fn call_addr(&mut self, opcode: u16) -> Result<(), Fault> {
         let addr = opcode & ADDR_MASK;
 
+        if self.stack.len() >= self.stack_depth {
+            return Err(Fault::StackOverflow);
+        }
+
         self.stack.push(self.pc);
         self.pc = addr;
+
+        Ok(())
     }
 
     // 3xkk
@@ -147,7 +210,7 @@ impl Instructions for Chip8 {
         let (x, kk) = get_xkk(opcode);
 
         if self.v[x] == kk {
-            self.pc += 2;
+            self.skip();
         }
     }
 
@@ -156,7 +219,7 @@ impl Instructions for Chip8 {
         let (x, kk) = get_xkk(opcode);
 
         if self.v[x] != kk {
-            self.pc += 2;
+            self.skip();
         }
     }
 
@@ -165,7 +228,7 @@ impl Instructions for Chip8 {
         let (x, y) = get_xy(opcode);
 
         if self.v[x] == self.v[y] {
-            self.pc += 2;
+            self.skip();
         }
     }
 
@@ -180,10 +243,8 @@ impl Instructions for Chip8 {
     fn add_vx_kk(&mut self, opcode: u16) {
         let (x, kk) = get_xkk(opcode);
 
-        // TODO Should it wrap ?
-        let (res, overflow) = self.v[x].overflowing_add(kk);
-        self.v[x] = res;
-        self.v[0xF] = if overflow { 1 } else { 0 };
+        // No carry flag, VF is left alone
+        self.v[x] = self.v[x].wrapping_add(kk);
     }
 
     // 8xy0
@@ -198,6 +259,10 @@ impl Instructions for Chip8 {
         let (x, y) = get_xy(opcode);
 
         self.v[x] |= self.v[y];
+
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
     }
 
     // 8xy2
@@ -205,6 +270,10 @@ impl Instructions for Chip8 {
         let (x, y) = get_xy(opcode);
 
         self.v[x] &= self.v[y];
+
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
     }
 
     // 8xy3
@@ -212,6 +281,10 @@ impl Instructions for Chip8 {
         let (x, y) = get_xy(opcode);
 
         self.v[x] ^= self.v[y];
+
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
     }
 
     // 8xy4
@@ -227,19 +300,24 @@ impl Instructions for Chip8 {
     fn sub_vx_vy(&mut self, opcode: u16) {
         let (x, y) = get_xy(opcode);
 
-        let (result, overflow) = self.v[x].overflowing_sub(self.v[y]);
+        let (result, borrow) = self.v[x].overflowing_sub(self.v[y]);
 
         self.v[x] = result;
-        self.v[0xF] = if overflow { 1 } else { 0 };
+        self.v[0xF] = if borrow { 0 } else { 1 };
     }
 
     // 8xy6
     fn shr_vx_vy(&mut self, opcode: u16) {
-        let (x, _) = get_xy(opcode);
+        let (x, y) = get_xy(opcode);
 
-        self.v[0xF] = self.v[x] & 1;
-        let (result, _) = self.v[x].overflowing_shr(1);
-        self.v[x] = result;
+        if self.quirks.shift_uses_vy {
+            self.v[x] = self.v[y];
+        }
+
+        // VF is written last, the flag wins when it is also the destination
+        let flag = self.v[x] & 1;
+        self.v[x] >>= 1;
+        self.v[0xF] = flag;
     }
 
     // 8xy7
@@ -254,11 +332,15 @@ impl Instructions for Chip8 {
 
     // 8xyE
     fn shl_vx_vy(&mut self, opcode: u16) {
-        let (x, _) = get_xy(opcode);
+        let (x, y) = get_xy(opcode);
 
-        self.v[0xF] = self.v[x] & 0x80;
-        let (result, _) = self.v[x].overflowing_shl(1);
-        self.v[x] = result;
+        if self.quirks.shift_uses_vy {
+            self.v[x] = self.v[y];
+        }
+
+        let flag = self.v[x] >> 7;
+        self.v[x] <<= 1;
+        self.v[0xF] = flag;
     }
 
     // 9xy0
@@ -266,7 +348,7 @@ impl Instructions for Chip8 {
         let (x, y) = get_xy(opcode);
 
         if self.v[x] != self.v[y] {
-            self.pc += 2;
+            self.skip();
         }
     }
 
@@ -280,51 +362,109 @@ impl Instructions for Chip8 {
     // Bnnn
     fn jp_v0_addr(&mut self, opcode: u16) {
         let addr = opcode & ADDR_MASK;
+        let (x, _) = get_xkk(opcode);
+        let offset = if self.quirks.jump_uses_vx {
+            self.v[x]
+        } else {
+            self.v[0]
+        };
 
-        self.pc = addr + self.v[0] as u16;
+        self.pc = addr + offset as u16;
     }
 
     // Cxkk
     fn rnd_vx_kk(&mut self, opcode: u16) {
         let (x, kk) = get_xkk(opcode);
 
-        let rnd: u8 = rand::thread_rng().gen();
+        let rnd = self.rng.next_u8();
 
         self.v[x] = rnd & kk;
     }
 
     // Dxyn
-    fn drw_vx_vy_nibble(&mut self, opcode: u16) {
+    fn drw_vx_vy_nibble(&mut self, opcode: u16) -> Result<(), Fault> {
         let (rx, ry, n) = get_xyn(opcode);
-        let data = self.mem.read_bytes(self.i, n);
+
+        if self.quirks.display_wait {
+            if !self.vblank {
+                // Execute this instruction again until the next frame
+                self.repeat();
+                return Ok(());
+            }
+
+            self.vblank = false;
+        }
+
+        // Dxy0 draws a 16x16 sprite on SUPER-CHIP
+        let (sprite_width, rows) = if n == 0 && self.variant != Variant::Chip8 {
+            (16, 16)
+        } else {
+            (8, n as usize)
+        };
+        let bytes_per_row = sprite_width / 8;
+        let sprite_len = rows * bytes_per_row;
+
+        // With two XO-CHIP planes selected, the sprite of the second one
+        // follows the sprite of the first one
+        let planes: Vec<u8> = [1, 2]
+            .into_iter()
+            .filter(|p| self.planes & p != 0)
+            .collect();
+        let data = self
+            .mem
+            .read_bytes(self.i, (sprite_len * planes.len()) as u8)?;
+
+        let (width, height) = (self.width(), self.height());
+        let (x0, y0) = (self.v[rx] as usize % width, self.v[ry] as usize % height);
+        let clip = self.quirks.clip_sprites;
 
         self.v[0xF] = 0;
-        data.iter().enumerate().for_each(|(j, byte)| {
-            let bits = byte_to_bit_array(*byte);
+        for (k, plane) in planes.into_iter().enumerate() {
+            let sprite = &data[k * sprite_len..(k + 1) * sprite_len];
 
-            for (i, bit) in bits.iter().enumerate() {
-                let x = (self.v[rx] as usize + i) % WIDTH;
-                let y = (self.v[ry] as usize + j) % HEIGHT;
+            for (j, row) in sprite.chunks(bytes_per_row).enumerate() {
+                for (b, byte) in row.iter().enumerate() {
+                    let bits = byte_to_bit_array(*byte);
 
-                let position = y * WIDTH + x;
+                    for (i, bit) in bits.iter().enumerate() {
+                        let (x, y) = (x0 + b * 8 + i, y0 + j);
 
-                let previous_value = self.gfx[position];
+                        if !bit || (clip && (x >= width || y >= height)) {
+                            continue;
+                        }
 
-                if previous_value && previous_value == *bit {
-                    self.v[0xF] = 1;
-                }
+                        let position = (y % height) * width + x % width;
 
-                self.gfx[position] ^= *bit;
+                        if self.gfx[position] & plane != 0 {
+                            self.v[0xF] = 1;
+                        }
+
+                        self.gfx[position] ^= plane;
+                    }
+                }
             }
+        }
+
+        self.last_draw = Some(Draw {
+            addr: self.i,
+            x: x0,
+            y: y0,
+            width: sprite_width,
+            rows,
+            planes: self.planes,
+            collision: self.v[0xF] == 1,
+            cycle: self.cycles,
         });
+
+        Ok(())
     }
 
     // Ex9E
     fn skp_vx(&mut self, opcode: u16) {
         let (x, _) = get_xkk(opcode);
 
-        if self.keys[x as usize] {
-            self.pc += 2;
+        if self.keys[x] {
+            self.skip();
         }
     }
 
@@ -332,8 +472,8 @@ impl Instructions for Chip8 {
     fn sknp_vx(&mut self, opcode: u16) {
         let (x, _) = get_xkk(opcode);
 
-        if !self.keys[x as usize] {
-            self.pc += 2;
+        if !self.keys[x] {
+            self.skip();
         }
     }
 
@@ -341,28 +481,39 @@ impl Instructions for Chip8 {
     fn ld_vx_dt(&mut self, opcode: u16) {
         let (x, _) = get_xkk(opcode);
 
-        self.v[x as usize] = self.delay_timer;
+        self.v[x] = self.delay_timer;
     }
 
     // Fx0A
     fn ld_vx_k(&mut self, opcode: u16) {
         let (x, _) = get_xkk(opcode);
-        self.paused = true;
-        self.target_register = Some(x);
+
+        // Execute this instruction again until a key has been released
+        match self.released_key.take() {
+            Some(key) => {
+                self.v[x] = key;
+                self.target_register = None;
+                self.pressed_key = None;
+            }
+            None => {
+                self.target_register = Some(x);
+                self.repeat();
+            }
+        }
     }
 
     // Fx15
     fn ld_dt_vx(&mut self, opcode: u16) {
         let (x, _) = get_xkk(opcode);
 
-        self.delay_timer = self.v[x as usize];
+        self.delay_timer = self.v[x];
     }
 
     // Fx18
     fn ld_st_vx(&mut self, opcode: u16) {
         let (x, _) = get_xkk(opcode);
 
-        self.sound_timer = self.v[x as usize];
+        self.sound_timer = self.v[x];
     }
 
     // Fx1E
@@ -370,7 +521,7 @@ impl Instructions for Chip8 {
         let (x, _) = get_xkk(opcode);
 
         // TODO Set overflow like on the Amiga implementation
-        self.i += self.v[x as usize] as u16;
+        self.i = self.i.wrapping_add(self.v[x] as u16);
     }
 
     // Fx29
@@ -383,38 +534,203 @@ impl Instructions for Chip8 {
     }
 
     // Fx33
-    fn ld_b_vx(&mut self, opcode: u16) {
+    fn ld_b_vx(&mut self, opcode: u16) -> Result<(), Fault> {
         let (x, _) = get_xkk(opcode);
 
         let addr = self.i;
         let value = self.v[x];
 
-        self.mem.set(addr, value / 100);
-        self.mem.set(addr + 1, (value % 100) / 10);
-        self.mem.set(addr + 2, value % 10);
+        self.mem.set(addr, value / 100)?;
+        self.mem.set(addr.wrapping_add(1), (value % 100) / 10)?;
+        self.mem.set(addr.wrapping_add(2), value % 10)?;
+
+        Ok(())
     }
 
     // Fx55
-    fn ld_addri_vx(&mut self, opcode: u16) {
+    fn ld_addri_vx(&mut self, opcode: u16) -> Result<(), Fault> {
         let (x, _) = get_xkk(opcode);
 
         for i in 0..=x {
-            let addr = self.i + i as u16;
-            self.mem.set(addr, self.v[i]);
+            let addr = self.i.wrapping_add(i as u16);
+            self.mem.set(addr, self.v[i])?;
         }
+
+        let increment = self.quirks.load_store_increments_i.amount(x);
+        self.i = self.i.wrapping_add(increment);
+
+        Ok(())
     }
 
     // Fx65
-    fn ld_vx_addri(&mut self, opcode: u16) {
+    fn ld_vx_addri(&mut self, opcode: u16) -> Result<(), Fault> {
         let (x, _) = get_xkk(opcode);
 
         for i in 0..=x {
-            let addr = self.i + i as u16;
-            self.v[i] = self.mem.get(addr);
+            let addr = self.i.wrapping_add(i as u16);
+            self.v[i] = self.mem.get(addr)?;
+        }
+
+        let increment = self.quirks.load_store_increments_i.amount(x);
+        self.i = self.i.wrapping_add(increment);
+
+        Ok(())
+    }
+
+    // 00Cn
+    fn scd_nibble(&mut self, opcode: u16) {
+        let n = (opcode & 0xF) as isize;
+
+        scroll(self, 0, n);
+    }
+
+    // 00FB
+    fn scr(&mut self) {
+        scroll(self, 4, 0);
+    }
+
+    // 00FC
+    fn scl(&mut self) {
+        scroll(self, -4, 0);
+    }
+
+    // 00FD
+    fn exit(&mut self) {
+        // Stay on this instruction
+        self.repeat();
+        self.paused = true;
+    }
+
+    // 00FE
+    fn low(&mut self) {
+        self.set_hires(false);
+    }
+
+    // 00FF
+    fn high(&mut self) {
+        self.set_hires(true);
+    }
+
+    // Fx30
+    fn ld_hf_vx(&mut self, opcode: u16) {
+        let (x, _) = get_xkk(opcode);
+        let font = self.v[x] & 0xF;
+
+        self.i = self.mem.get_big_font_address(font);
+    }
+
+    // Fx75
+    fn ld_r_vx(&mut self, opcode: u16) {
+        let (x, _) = get_xkk(opcode);
+
+        self.rpl[..=x].copy_from_slice(&self.v[..=x]);
+    }
+
+    // Fx85
+    fn ld_vx_r(&mut self, opcode: u16) {
+        let (x, _) = get_xkk(opcode);
+
+        self.v[..=x].copy_from_slice(&self.rpl[..=x]);
+    }
+
+    // 00Dn
+    fn scu_nibble(&mut self, opcode: u16) {
+        let n = (opcode & 0xF) as isize;
+
+        scroll(self, 0, -n);
+    }
+
+    // 5xy2
+    fn ld_addri_vx_vy(&mut self, opcode: u16) -> Result<(), Fault> {
+        let (x, y) = get_xy(opcode);
+
+        for (offset, r) in register_range(x, y).into_iter().enumerate() {
+            self.mem
+                .set(self.i.wrapping_add(offset as u16), self.v[r])?;
+        }
+
+        Ok(())
+    }
+
+    // 5xy3
+    fn ld_vx_vy_addri(&mut self, opcode: u16) -> Result<(), Fault> {
+        let (x, y) = get_xy(opcode);
+
+        for (offset, r) in register_range(x, y).into_iter().enumerate() {
+            self.v[r] = self.mem.get(self.i.wrapping_add(offset as u16))?;
+        }
+
+        Ok(())
+    }
+
+    // F000 nnnn
+    fn ld_i_long(&mut self) -> Result<(), Fault> {
+        let h = self.mem.get(self.pc)?;
+        let l = self.mem.get(self.pc.wrapping_add(1))?;
+
+        self.i = bytes_to_word(h, l);
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(())
+    }
+
+    // Fn01
+    fn plane_n(&mut self, opcode: u16) {
+        let (n, _) = get_xkk(opcode);
+
+        self.planes = n as u8 & 0b11;
+    }
+
+    // F002
+    fn audio(&mut self) -> Result<(), Fault> {
+        let mut pattern = [0; 16];
+        pattern.copy_from_slice(&self.mem.read_bytes(self.i, 16)?);
+
+        self.pattern = Some(pattern);
+
+        Ok(())
+    }
+
+    // Fx3A
+    fn pitch_vx(&mut self, opcode: u16) {
+        let (x, _) = get_xkk(opcode);
+
+        self.pitch = self.v[x];
+    }
+}
+
+/// Moves the selected planes of the display by `dx` and `dy` pixels, blank
+/// pixels come in from the edges.
+fn scroll(chip8: &mut Chip8, dx: isize, dy: isize) {
+    let (width, height) = (chip8.width() as isize, chip8.height() as isize);
+    let planes = chip8.planes;
+    let previous = chip8.gfx.clone();
+
+    for y in 0..height {
+        for x in 0..width {
+            let (from_x, from_y) = (x - dx, y - dy);
+            let scrolled = if (0..width).contains(&from_x) && (0..height).contains(&from_y) {
+                previous[(from_y * width + from_x) as usize]
+            } else {
+                0
+            };
+
+            let pixel = &mut chip8.gfx[(y * width + x) as usize];
+            *pixel = (*pixel & !planes) | (scrolled & planes);
         }
     }
 }
 
+/// Registers from Vx to Vy for `5xy2` and `5xy3`, in descending order when
+/// x is greater than y.
+fn register_range(x: usize, y: usize) -> Vec<usize> {
+    if x <= y {
+        (x..=y).collect()
+    } else {
+        (y..=x).rev().collect()
+    }
+}
+
 pub fn bytes_to_word(h: u8, l: u8) -> u16 {
     ((h as u16) << 8) | l as u16
 }
@@ -460,7 +776,15 @@ fn byte_to_bit_array(b: u8) -> [bool; 8] {
 
 #[cfg(test)]
 mod tests {
-    use crate::instr::byte_to_bit_array;
+    use crate::chip8::{Chip8, Draw, HIRES_WIDTH, WIDTH};
+    use crate::error::Fault;
+    use crate::instr::{byte_to_bit_array, Instructions};
+    use crate::mem::Mem;
+    use crate::quirks::{IndexIncrement, Quirks};
+    use crate::rng::Rng;
+    use crate::variant::Variant;
+
+    type Op = fn(&mut Chip8, u16);
 
     #[test]
     fn test_byte_to_bit_array() {
@@ -469,4 +793,565 @@ mod tests {
             [true, true, false, false, false, false, false, true]
         );
     }
+
+    #[test]
+    fn test_cls() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.gfx[10] = 1;
+
+        chip8.cls();
+        assert!(chip8.gfx.iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn test_call_ret() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.pc = 0x202;
+
+        chip8.call_addr(0x2ABC).unwrap();
+        assert_eq!((chip8.pc, chip8.stack.as_slice()), (0xABC, &[0x202][..]));
+        chip8.pc = 0xABE;
+        chip8.call_addr(0x2DEF).unwrap();
+        assert_eq!(chip8.stack, [0x202, 0xABE]);
+
+        chip8.ret().unwrap();
+        assert_eq!(chip8.pc, 0xABE);
+        chip8.ret().unwrap();
+        assert_eq!(chip8.pc, 0x202);
+        assert_eq!(chip8.ret(), Err(Fault::StackUnderflow));
+    }
+
+    #[test]
+    fn test_jumps() {
+        let mut chip8 = Chip8::new(Mem::new());
+
+        chip8.jp_addr(0x1345);
+        assert_eq!(chip8.pc, 0x345);
+
+        chip8.v[0] = 0x10;
+        chip8.v[3] = 0x20;
+        chip8.jp_v0_addr(0xB300);
+        assert_eq!(chip8.pc, 0x310);
+
+        chip8.quirks.jump_uses_vx = true;
+        chip8.jp_v0_addr(0xB300);
+        assert_eq!(chip8.pc, 0x320);
+    }
+
+    #[test]
+    fn test_skips() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0x42;
+        chip8.v[2] = 0x42;
+        chip8.v[3] = 0x43;
+        chip8.keys[0x3] = true;
+
+        let skips = |chip8: &mut Chip8, f: Op, opcode: u16| {
+            chip8.pc = 0x200;
+            f(chip8, opcode);
+            chip8.pc == 0x202
+        };
+
+        assert!(skips(&mut chip8, Chip8::se_vx_kk, 0x3142));
+        assert!(!skips(&mut chip8, Chip8::se_vx_kk, 0x3143));
+        assert!(skips(&mut chip8, Chip8::sne_vx_kk, 0x4143));
+        assert!(!skips(&mut chip8, Chip8::sne_vx_kk, 0x4142));
+        assert!(skips(&mut chip8, Chip8::se_vx_vy, 0x5120));
+        assert!(!skips(&mut chip8, Chip8::se_vx_vy, 0x5130));
+        assert!(skips(&mut chip8, Chip8::sne_vx_vy, 0x9130));
+        assert!(!skips(&mut chip8, Chip8::sne_vx_vy, 0x9120));
+        assert!(skips(&mut chip8, Chip8::skp_vx, 0xE39E));
+        assert!(!skips(&mut chip8, Chip8::skp_vx, 0xE19E));
+        assert!(skips(&mut chip8, Chip8::sknp_vx, 0xE1A1));
+        assert!(!skips(&mut chip8, Chip8::sknp_vx, 0xE3A1));
+    }
+
+    #[test]
+    fn test_loads() {
+        let mut chip8 = Chip8::new(Mem::new());
+
+        chip8.ld_vx_kk(0x6A12);
+        assert_eq!(chip8.v[0xA], 0x12);
+        chip8.ld_vx_vy(0x8BA0);
+        assert_eq!(chip8.v[0xB], 0x12);
+        chip8.ld_i_addr(0xA456);
+        assert_eq!(chip8.i, 0x456);
+    }
+
+    #[test]
+    fn test_add_vx_kk() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0xFF;
+
+        chip8.add_vx_kk(0x7102);
+        assert_eq!(chip8.v[1], 0x01);
+    }
+
+    #[test]
+    fn test_add_vx_kk_leaves_vf() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0xFF;
+        chip8.v[0xF] = 0x55;
+
+        chip8.add_vx_kk(0x7102);
+        assert_eq!(chip8.v[0xF], 0x55);
+    }
+
+    #[test]
+    fn test_logic_ops() {
+        let mut chip8 = Chip8::new(Mem::new());
+        let ops: [(Op, u8); 3] = [
+            (Chip8::or_vx_vy, 0b1110),
+            (Chip8::and_vx_vy, 0b1000),
+            (Chip8::xor_vx_vy, 0b0110),
+        ];
+
+        for (op, expected) in ops {
+            chip8.v[1] = 0b1100;
+            chip8.v[2] = 0b1010;
+            chip8.v[0xF] = 1;
+
+            op(&mut chip8, 0x8120);
+            assert_eq!((chip8.v[1], chip8.v[0xF]), (expected, 0));
+        }
+
+        chip8.quirks.vf_reset = false;
+        chip8.v[0xF] = 1;
+        chip8.or_vx_vy(0x8120);
+        assert_eq!(chip8.v[0xF], 1);
+    }
+
+    #[test]
+    fn test_add_vx_vy() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0xF0;
+        chip8.v[2] = 0x0F;
+
+        chip8.add_vx_vy(0x8124);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0xFF, 0));
+        chip8.add_vx_vy(0x8124);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0x0E, 1));
+
+        // The flag wins when VF is the destination
+        chip8.v[0xF] = 0x80;
+        chip8.v[2] = 0x80;
+        chip8.add_vx_vy(0x8F24);
+        assert_eq!(chip8.v[0xF], 1);
+    }
+
+    #[test]
+    fn test_sub_vx_vy() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0x10;
+        chip8.v[2] = 0x30;
+
+        chip8.sub_vx_vy(0x8125);
+        assert_eq!(chip8.v[1], 0xE0);
+        chip8.sub_vx_vy(0x8125);
+        assert_eq!(chip8.v[1], 0xB0);
+    }
+
+    #[test]
+    fn test_sub_vx_vy_flag() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0x10;
+        chip8.v[2] = 0x30;
+
+        chip8.sub_vx_vy(0x8125);
+        assert_eq!(chip8.v[0xF], 0);
+        chip8.sub_vx_vy(0x8125);
+        assert_eq!(chip8.v[0xF], 1);
+    }
+
+    #[test]
+    fn test_subn_vx_vy() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0x30;
+        chip8.v[2] = 0x10;
+
+        chip8.subn_vx_vy(0x8127);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0xE0, 0));
+        chip8.v[1] = 0x08;
+        chip8.subn_vx_vy(0x8127);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0x08, 1));
+    }
+
+    #[test]
+    fn test_shifts() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.quirks.shift_uses_vy = false;
+        chip8.v[1] = 0b1000_0011;
+
+        chip8.shr_vx_vy(0x8106);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0b0100_0001, 1));
+        chip8.shr_vx_vy(0x8106);
+        chip8.shr_vx_vy(0x8106);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0b0001_0000, 0));
+
+        chip8.v[1] = 0b0100_0001;
+        chip8.shl_vx_vy(0x810E);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0b1000_0010, 0));
+        chip8.shl_vx_vy(0x810E);
+        assert_eq!(chip8.v[1], 0b0000_0100);
+    }
+
+    #[test]
+    fn test_shl_flag() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0b1000_0000;
+
+        chip8.shl_vx_vy(0x811E);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0, 1));
+    }
+
+    /// Reference model of the `8xyn` opcodes with the COSMAC VIP quirks:
+    /// `(Vx, VF)`, VF being `None` when left unchanged.
+    fn alu_reference(n: u16, vx: u8, vy: u8) -> (u8, Option<u8>) {
+        match n {
+            0x0 => (vy, None),
+            0x1 => (vx | vy, Some(0)),
+            0x2 => (vx & vy, Some(0)),
+            0x3 => (vx ^ vy, Some(0)),
+            0x4 => (
+                vx.wrapping_add(vy),
+                Some((vx as u16 + vy as u16 > 0xFF) as u8),
+            ),
+            0x5 => (vx.wrapping_sub(vy), Some((vx >= vy) as u8)),
+            0x6 => (vy >> 1, Some(vy & 1)),
+            0x7 => (vy.wrapping_sub(vx), Some((vy >= vx) as u8)),
+            0xE => (vy << 1, Some(vy >> 7)),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_alu_against_reference() {
+        let mut chip8 = Chip8::new(Mem::new());
+
+        for n in [0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE] {
+            for vx in 0..=255 {
+                for vy in 0..=255 {
+                    let (result, flag) = alu_reference(n, vx, vy);
+
+                    // VF is written after the result when it is the destination
+                    for x in [0x1, 0xF] {
+                        let opcode = 0x8000 | x << 8 | 0x20 | n;
+                        chip8.v[x as usize] = vx;
+                        chip8.v[2] = vy;
+                        if x != 0xF {
+                            chip8.v[0xF] = 0xAA;
+                        }
+
+                        chip8.pc = 0x200;
+                        chip8.mem.set(0x200, (opcode >> 8) as u8).unwrap();
+                        chip8.mem.set(0x201, opcode as u8).unwrap();
+                        chip8.execute().unwrap();
+
+                        let expected = match (x, flag) {
+                            (0xF, Some(flag)) => (flag, flag),
+                            (0xF, None) => (result, result),
+                            (_, flag) => (result, flag.unwrap_or(0xAA)),
+                        };
+                        assert_eq!(
+                            (chip8.v[x as usize], chip8.v[0xF]),
+                            expected,
+                            "{:04X} with Vx = {:#04X}, Vy = {:#04X}",
+                            opcode,
+                            vx,
+                            vy
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_add_vx_kk_against_reference() {
+        let mut chip8 = Chip8::new(Mem::new());
+
+        for vx in 0..=255 {
+            for kk in 0..=255u8 {
+                chip8.v[1] = vx;
+                chip8.v[0xF] = 0xAA;
+
+                chip8.add_vx_kk(0x7100 | kk as u16);
+                assert_eq!((chip8.v[1], chip8.v[0xF]), (vx.wrapping_add(kk), 0xAA));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rnd_masks() {
+        let mut chip8 = Chip8::new(Mem::new());
+
+        for _ in 0..100 {
+            chip8.rnd_vx_kk(0xC10F);
+            assert_eq!(chip8.v[1] & 0xF0, 0);
+        }
+
+        chip8.rnd_vx_kk(0xC100);
+        assert_eq!(chip8.v[1], 0);
+
+        // The same seed draws the same numbers
+        let draws = |seed| {
+            let mut chip8 = Chip8::new(Mem::new());
+            chip8.rng = Rng::new(seed);
+            (0..16)
+                .map(|_| {
+                    chip8.rnd_vx_kk(0xC1FF);
+                    chip8.v[1]
+                })
+                .collect::<Vec<u8>>()
+        };
+        assert_eq!(draws(7), draws(7));
+        assert_ne!(draws(7), draws(8));
+    }
+
+    #[test]
+    fn test_drw_collision() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.quirks.display_wait = false;
+        chip8.mem.set(0x300, 0b1010_0000).unwrap();
+        chip8.mem.set(0x301, 0b0100_0000).unwrap();
+        chip8.i = 0x300;
+        chip8.v[1] = 2;
+        chip8.v[2] = 3;
+
+        chip8.drw_vx_vy_nibble(0xD122).unwrap();
+        assert_eq!(chip8.v[0xF], 0);
+        assert_eq!(chip8.gfx[3 * WIDTH + 2..3 * WIDTH + 5], [1, 0, 1]);
+        assert_eq!(chip8.gfx[4 * WIDTH + 2..4 * WIDTH + 5], [0, 1, 0]);
+
+        // Only the first row overlaps
+        chip8.i = 0x301;
+        chip8.drw_vx_vy_nibble(0xD121).unwrap();
+        assert_eq!(chip8.v[0xF], 0);
+        chip8.i = 0x300;
+        chip8.drw_vx_vy_nibble(0xD121).unwrap();
+        assert_eq!(chip8.v[0xF], 1);
+        assert_eq!(chip8.gfx[3 * WIDTH + 2..3 * WIDTH + 5], [0, 1, 0]);
+        assert_eq!(
+            chip8.last_draw,
+            Some(Draw {
+                addr: 0x300,
+                x: 2,
+                y: 3,
+                width: 8,
+                rows: 1,
+                planes: 1,
+                collision: true,
+                cycle: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_timers() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 3;
+
+        chip8.ld_dt_vx(0xF115);
+        chip8.ld_st_vx(0xF118);
+        chip8.tick_timers();
+        chip8.ld_vx_dt(0xF207);
+
+        assert_eq!((chip8.v[2], chip8.sound_timer), (2, 2));
+    }
+
+    #[test]
+    fn test_add_i_vx() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.i = 0xFFF;
+        chip8.v[1] = 2;
+
+        chip8.add_i_vx(0xF11E);
+        assert_eq!(chip8.i, 0x1001);
+    }
+
+    #[test]
+    fn test_ld_f_vx() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0x1A;
+
+        chip8.ld_f_vx(0xF129);
+        assert_eq!(chip8.i, chip8.mem.get_font_address(0xA));
+    }
+
+    #[test]
+    fn test_ld_b_vx() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.i = 0x300;
+
+        for (value, digits) in [(234, [2, 3, 4]), (7, [0, 0, 7]), (60, [0, 6, 0])] {
+            chip8.v[5] = value;
+            chip8.ld_b_vx(0xF533).unwrap();
+            assert_eq!(chip8.mem.read_bytes(0x300, 3), Ok(digits.to_vec()));
+        }
+        assert_eq!(chip8.i, 0x300);
+    }
+
+    #[test]
+    fn test_load_store() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.i = 0x300;
+        chip8.v[..4].copy_from_slice(&[1, 2, 3, 4]);
+
+        chip8.ld_addri_vx(0xF255).unwrap();
+        assert_eq!(chip8.i, 0x303);
+        assert_eq!(chip8.mem.read_bytes(0x300, 4), Ok(vec![1, 2, 3, 0]));
+
+        chip8.quirks.load_store_increments_i = IndexIncrement::None;
+        chip8.i = 0x301;
+        chip8.ld_vx_addri(0xF165).unwrap();
+        assert_eq!(chip8.i, 0x301);
+        assert_eq!(chip8.v[..4], [2, 3, 3, 4]);
+
+        // CHIP-48 stops one short
+        chip8.quirks = Quirks::CHIP_48;
+        chip8.ld_vx_addri(0xF165).unwrap();
+        assert_eq!(chip8.i, 0x302);
+    }
+
+    #[test]
+    fn test_shift_quirk() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[1] = 0b0000_0010;
+        chip8.v[2] = 0b0000_0101;
+
+        chip8.quirks.shift_uses_vy = false;
+        chip8.shr_vx_vy(0x8126);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0b0000_0001, 0));
+
+        chip8.quirks.shift_uses_vy = true;
+        chip8.shr_vx_vy(0x8126);
+        assert_eq!((chip8.v[1], chip8.v[0xF]), (0b0000_0010, 1));
+    }
+
+    #[test]
+    fn test_clip_sprites_quirk() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.quirks = Quirks::CHIP_48;
+        chip8.mem.set(0x300, 0xFF).unwrap();
+        chip8.i = 0x300;
+        chip8.v[0] = WIDTH as u8 - 4;
+
+        chip8.drw_vx_vy_nibble(0xD011).unwrap();
+        assert_eq!(chip8.gfx[WIDTH - 1], 1);
+        assert_eq!(chip8.gfx[0], 0);
+
+        chip8.quirks.clip_sprites = false;
+        chip8.cls();
+        chip8.drw_vx_vy_nibble(0xD011).unwrap();
+        assert_eq!(chip8.gfx[WIDTH - 1], 1);
+        assert_eq!(chip8.gfx[0], 1);
+    }
+
+    #[test]
+    fn test_display_wait_quirk() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.quirks.display_wait = true;
+        chip8.pc = 0x202;
+
+        chip8.drw_vx_vy_nibble(0xD001).unwrap();
+        assert_eq!(chip8.pc, 0x202);
+
+        chip8.drw_vx_vy_nibble(0xD001).unwrap();
+        assert_eq!(chip8.pc, 0x200);
+
+        chip8.tick_timers();
+        chip8.pc = 0x202;
+        chip8.drw_vx_vy_nibble(0xD001).unwrap();
+        assert_eq!(chip8.pc, 0x202);
+    }
+
+    #[test]
+    fn test_superchip_big_sprite() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.variant = Variant::SuperChip;
+        chip8.high();
+        assert_eq!(chip8.gfx.len(), HIRES_WIDTH * 64);
+
+        // 16x16 sprite with only its last row set
+        chip8.i = 0x300;
+        chip8.mem.set(0x31E, 0xFF).unwrap();
+        chip8.mem.set(0x31F, 0xFF).unwrap();
+        chip8.drw_vx_vy_nibble(0xD000).unwrap();
+
+        let row = &chip8.gfx[15 * HIRES_WIDTH..16 * HIRES_WIDTH];
+        assert_eq!(row.iter().filter(|&&p| p == 1).count(), 16);
+        assert!(chip8.gfx[..15 * HIRES_WIDTH].iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn test_superchip_scroll() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.gfx[0] = 1;
+
+        chip8.scr();
+        assert_eq!(chip8.gfx[4], 1);
+        chip8.scd_nibble(0x00C2);
+        assert_eq!(chip8.gfx[2 * WIDTH + 4], 1);
+        chip8.scl();
+        assert_eq!(chip8.gfx[2 * WIDTH], 1);
+        assert_eq!(chip8.gfx.iter().filter(|&&p| p != 0).count(), 1);
+
+        chip8.scl();
+        assert!(chip8.gfx.iter().all(|&p| p == 0));
+    }
+
+    #[test]
+    fn test_superchip_rpl_flags() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.v[..3].copy_from_slice(&[1, 2, 3]);
+
+        chip8.ld_r_vx(0xF175);
+        chip8.v = [0; 16];
+        chip8.ld_vx_r(0xF285);
+
+        assert_eq!(chip8.v[..3], [1, 2, 0]);
+    }
+
+    #[test]
+    fn test_xochip_planes() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.variant = Variant::XoChip;
+        chip8.quirks = Quirks::XO_CHIP;
+        chip8.i = 0x300;
+        chip8.mem.set(0x300, 0x80).unwrap();
+        chip8.mem.set(0x301, 0xC0).unwrap();
+
+        // Both planes, one row each
+        chip8.plane_n(0xF301);
+        chip8.drw_vx_vy_nibble(0xD001).unwrap();
+        assert_eq!(chip8.gfx[..3], [3, 2, 0]);
+        assert_eq!(chip8.v[0xF], 0);
+        assert_eq!(chip8.last_draw.map(|draw| draw.planes), Some(3));
+
+        // Only the second plane is cleared and scrolled
+        chip8.plane_n(0xF201);
+        chip8.scr();
+        assert_eq!(chip8.gfx[..6], [1, 0, 0, 0, 2, 2]);
+        chip8.cls();
+        assert_eq!(chip8.gfx[..6], [1, 0, 0, 0, 0, 0]);
+
+        chip8.plane_n(0xF101);
+        chip8.drw_vx_vy_nibble(0xD001).unwrap();
+        assert_eq!(chip8.v[0xF], 1);
+    }
+
+    #[test]
+    fn test_xochip_register_ranges() {
+        let mut chip8 = Chip8::new(Mem::new());
+        chip8.i = 0x300;
+        chip8.v[2..5].copy_from_slice(&[1, 2, 3]);
+
+        chip8.ld_addri_vx_vy(0x5242).unwrap();
+        assert_eq!(chip8.mem.read_bytes(0x300, 3), Ok(vec![1, 2, 3]));
+
+        // Reversed range
+        chip8.ld_vx_vy_addri(0x5863).unwrap();
+        assert_eq!(chip8.v[6..9], [3, 2, 1]);
+        assert_eq!(chip8.i, 0x300);
+    }
 }
@@ -0,0 +1,57 @@
+//! Core of the Chipr emulator.
+//!
+//! This crate has no dependency on any GUI toolkit, the egui application is
+//! only one consumer of it. A minimal frontend looks like this:
+//!
+//! ```no_run
+//! use chipr::{Chip8, Clock};
+//!
+//! let rom = std::fs::read("game.ch8").unwrap();
+//! let mut chip8 = Chip8::from_rom(&rom).unwrap();
+//! let mut clock = Clock::default();
+//!
+//! chip8.paused = false;
+//! chip8.set_key(0x5, true);
+//!
+//! // One second: each frame runs instructions then ticks the timers, which
+//! // `Dxyn` waits for with the default quirks
+//! for _ in 0..60 {
+//!     clock.frame(&mut chip8).unwrap();
+//! }
+//!
+//! for row in chip8.framebuffer().chunks(chip8.width()) {
+//!     for pixel in row {
+//!         print!("{}", if *pixel != 0 { '#' } else { ' ' });
+//!     }
+//!     println!();
+//! }
+//! ```
+
+pub mod audio;
+pub mod breakpoint;
+pub mod chip8;
+pub mod clock;
+pub mod disasm;
+pub mod display;
+pub mod error;
+pub mod font;
+pub mod hash;
+pub mod instr;
+pub mod mem;
+pub mod movie;
+pub mod quirks;
+pub mod rewind;
+pub mod rng;
+pub mod state;
+pub mod trace;
+pub mod variant;
+
+pub use breakpoint::Breakpoint;
+pub use chip8::{Chip8, HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
+pub use clock::Clock;
+pub use error::{ExecError, LoadError, StateError};
+pub use font::Font;
+pub use mem::{AddressMode, Mem, RAM_SIZE, XO_RAM_SIZE};
+pub use quirks::{IndexIncrement, Quirks};
+pub use rewind::Rewind;
+pub use variant::Variant;
@@ -1,56 +1,214 @@
+use crate::error::{LoadError, MemFault};
+use crate::font::Font;
+
 pub const RAM_SIZE: usize = 4096;
+pub const XO_RAM_SIZE: usize = 0x10000;
+pub const PROGRAM_START: usize = 0x200;
+/// Where the small font is loaded, right before the big one.
+pub const FONT_ADDRESS: usize = 0x50;
+pub const BIG_FONT_ADDRESS: usize = FONT_ADDRESS + 80;
+
+/// SUPER-CHIP 8x10 digits, A to F come from Octo.
+const BIG_FONT: [u8; 160] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+/// What happens when an instruction accesses memory past the end of `ram`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressMode {
+    /// Addresses wrap around at the end of memory (4 KiB, 64 KiB on
+    /// XO-CHIP), like the address masking of the original hardware.
+    Wrap,
+    /// The access fails with a `MemFault`.
+    Fault,
+}
 
 pub struct Mem {
-    pub ram: [u8; RAM_SIZE],
+    /// `RAM_SIZE` bytes, or `XO_RAM_SIZE` on XO-CHIP.
+    pub ram: Vec<u8>,
+    pub address_mode: AddressMode,
+    /// Addresses written by the last instruction, checked by watchpoints.
+    pub writes: Vec<usize>,
+    /// `Chip8::cycles` after the last write to each byte, 0 if it was never
+    /// written by an instruction.
+    pub written_at: Vec<u64>,
+}
+
+impl Default for Mem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Mem {
     pub fn new() -> Self {
-        let fonts: [u8; 80] = [
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-        ];
+        Self::with_size(RAM_SIZE)
+    }
+
+    pub fn with_size(size: usize) -> Self {
+        let mut ram = vec![0; size];
+        let written_at = vec![0; size];
+
+        ram[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
 
-        let mut ram: [u8; RAM_SIZE] = [0; RAM_SIZE];
+        let mut mem = Mem {
+            ram,
+            address_mode: AddressMode::Fault,
+            writes: Vec::new(),
+            written_at,
+        };
+        mem.set_font(Font::default());
 
-        ram[0x50..0xA0].copy_from_slice(&fonts[..0x50]);
+        mem
+    }
+
+    /// Replaces the small font sprites pointed at by `Fx29`.
+    pub fn set_font(&mut self, font: Font) {
+        self.ram[FONT_ADDRESS..FONT_ADDRESS + 80].copy_from_slice(font.sprites());
+    }
+
+    /// The font set with `set_font`, unless its sprites were overwritten.
+    pub fn font(&self) -> Option<Font> {
+        let sprites = &self.ram[FONT_ADDRESS..FONT_ADDRESS + 80];
+
+        Font::ALL
+            .iter()
+            .map(|&(_, font)| font)
+            .find(|font| sprites == font.sprites())
+    }
+
+    pub fn load_rom(&mut self, rom_data: &[u8]) -> Result<(), LoadError> {
+        let max = self.ram.len() - PROGRAM_START;
+
+        if rom_data.len() > max {
+            return Err(LoadError::RomTooLarge {
+                size: rom_data.len(),
+                max,
+            });
+        }
 
-        Mem { ram }
+        self.ram[PROGRAM_START..PROGRAM_START + rom_data.len()].copy_from_slice(rom_data);
+
+        Ok(())
     }
 
-    pub fn load_rom(&mut self, rom_data: Vec<u8>) {
-        rom_data.iter().enumerate().for_each(|(i, byte)| {
-            self.ram[0x200 + i] = *byte;
-        });
+    pub fn get(&self, address: u16) -> Result<u8, MemFault> {
+        Ok(self.ram[self.resolve(address as usize)?])
     }
 
-    pub fn get(&self, address: u16) -> u8 {
-        self.ram[address as usize]
+    pub fn set(&mut self, address: u16, value: u8) -> Result<(), MemFault> {
+        let address = self.resolve(address as usize)?;
+        self.ram[address] = value;
+        self.writes.push(address);
+
+        Ok(())
     }
 
-    pub fn set(&mut self, address: u16, value: u8) {
-        self.ram[address as usize] = value;
+    pub fn read_bytes(&self, address: u16, n: u8) -> Result<Vec<u8>, MemFault> {
+        (0..n as usize)
+            .map(|offset| Ok(self.ram[self.resolve(address as usize + offset)?]))
+            .collect()
     }
 
-    pub fn read_bytes(&self, address: u16, n: u8) -> &[u8] {
-        &self.ram[address as usize..address as usize + n as usize]
+    fn resolve(&self, address: usize) -> Result<usize, MemFault> {
+        match self.address_mode {
+            AddressMode::Wrap => Ok(address % self.ram.len()),
+            AddressMode::Fault if address < self.ram.len() => Ok(address),
+            AddressMode::Fault => Err(MemFault { address }),
+        }
     }
 
     pub fn get_font_address(&self, font: u8) -> u16 {
-        font as u16 * 5
+        (FONT_ADDRESS + font as usize * 5) as u16
+    }
+
+    pub fn get_big_font_address(&self, font: u8) -> u16 {
+        (BIG_FONT_ADDRESS + font as usize * 10) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{LoadError, MemFault};
+    use crate::font::Font;
+    use crate::mem::{AddressMode, Mem, RAM_SIZE, XO_RAM_SIZE};
+
+    #[test]
+    fn test_load_rom_too_large() {
+        let mut mem = Mem::new();
+
+        assert!(mem.load_rom(&[0xAA; RAM_SIZE - 0x200]).is_ok());
+        assert_eq!(
+            mem.load_rom(&[0xAA; RAM_SIZE - 0x200 + 1]),
+            Err(LoadError::RomTooLarge {
+                size: RAM_SIZE - 0x200 + 1,
+                max: RAM_SIZE - 0x200
+            })
+        );
+    }
+
+    #[test]
+    fn test_address_modes() {
+        let mut mem = Mem::new();
+        mem.set(0xFFF, 1).unwrap();
+        mem.set(0x000, 2).unwrap();
+
+        assert_eq!(mem.get(0x1000), Err(MemFault { address: 0x1000 }));
+        assert_eq!(mem.read_bytes(0xFFF, 2), Err(MemFault { address: 0x1000 }));
+
+        mem.address_mode = AddressMode::Wrap;
+        assert_eq!(mem.get(0x1000), Ok(2));
+        assert_eq!(mem.read_bytes(0xFFF, 2), Ok(vec![1, 2]));
+    }
+
+    #[test]
+    fn test_xo_chip_memory() {
+        let mut mem = Mem::with_size(XO_RAM_SIZE);
+
+        assert!(mem.load_rom(&[0xAA; XO_RAM_SIZE - 0x200]).is_ok());
+        mem.set(0xFFFF, 1).unwrap();
+        assert_eq!(mem.get(0xFFFF), Ok(1));
+        assert_eq!(
+            mem.read_bytes(0xFFFF, 2),
+            Err(MemFault { address: 0x10000 })
+        );
+    }
+
+    #[test]
+    fn test_font_address() {
+        let mut mem = Mem::new();
+        let zero = mem.get_font_address(0);
+        let f = mem.get_font_address(0xF);
+
+        assert_eq!(
+            mem.read_bytes(zero, 5),
+            Ok(vec![0xF0, 0x90, 0x90, 0x90, 0xF0])
+        );
+        assert_eq!(mem.read_bytes(f, 5), Ok(vec![0xF0, 0x80, 0xF0, 0x80, 0x80]));
+
+        mem.set_font(Font::Dream6800);
+        assert_eq!(
+            mem.read_bytes(zero, 5),
+            Ok(vec![0xE0, 0xA0, 0xA0, 0xA0, 0xE0])
+        );
+        assert_eq!(mem.font(), Some(Font::Dream6800));
+
+        mem.set(zero, 0xFF).unwrap();
+        assert_eq!(mem.font(), None);
     }
 }
@@ -0,0 +1,457 @@
+//! Input recordings which replay a run exactly.
+//!
+//! A movie holds everything a run depends on besides the ROM: the settings,
+//! the random seed and the keys held in each 60 Hz frame. Movies are text
+//! files:
+//!
+//! ```text
+//! chipr-movie 1
+//! rom 3C0BD7A1E24F9D85
+//! seed 12345
+//! variant CHIP-8
+//! font SUPER-CHIP
+//! quirks shift_uses_vy vf_reset display_wait load_store_increments_i
+//! memory fault
+//! stack-depth 16
+//! hz 700
+//! frames
+//! 0000 120
+//! 0020 8
+//! ```
+//!
+//! `rom` is the FNV-1a hash of the ROM, `quirks` lists the quirks turned on,
+//! with `load_store_increments_i` for the VIP increment of I and
+//! `load_store_increments_i_by_x` for the CHIP-48 one, and each line after
+//! `frames` is the keys held, bit n for key n, and the number of frames they
+//! are held for.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::chip8::Chip8;
+use crate::font::Font;
+use crate::hash::fnv1a;
+use crate::mem::AddressMode;
+use crate::quirks::{IndexIncrement, Quirks};
+use crate::rng::Rng;
+use crate::variant::Variant;
+
+const MAGIC: &str = "chipr-movie";
+pub const MOVIE_VERSION: u32 = 1;
+/// Longest movie loaded, 24 hours of frames, so a corrupt frame count does not
+/// exhaust the memory.
+pub const MAX_FRAMES: usize = 24 * 60 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    /// FNV-1a hash of the ROM.
+    pub rom_hash: u64,
+    pub seed: u64,
+    pub variant: Variant,
+    pub font: Font,
+    pub quirks: Quirks,
+    pub address_mode: AddressMode,
+    pub stack_depth: usize,
+    pub cpu_hz: u32,
+    /// Keys held in each frame, bit n for key n.
+    pub frames: Vec<u16>,
+}
+
+impl Movie {
+    /// An empty movie of `chip8`, which should have just been started from
+    /// the ROM with the hash `rom_hash`. The font is not part of the
+    /// machine, it is given separately.
+    pub fn new(rom_hash: u64, chip8: &Chip8, font: Font, cpu_hz: u32) -> Self {
+        Movie {
+            rom_hash,
+            seed: chip8.rng.seed,
+            variant: chip8.variant,
+            font,
+            quirks: chip8.quirks,
+            address_mode: chip8.mem.address_mode,
+            stack_depth: chip8.stack_depth,
+            cpu_hz,
+            frames: Vec::new(),
+        }
+    }
+
+    /// A running machine in the state the movie starts from. Fails if `rom`
+    /// is not the one the movie was recorded with.
+    pub fn start(&self, rom: &[u8]) -> Result<Chip8, String> {
+        let hash = fnv1a(rom);
+        if hash != self.rom_hash {
+            return Err(format!(
+                "the movie was recorded with the ROM {:016X}, not {:016X}",
+                self.rom_hash, hash
+            ));
+        }
+
+        let mut chip8 =
+            Chip8::from_rom_with_variant(rom, self.variant).map_err(|e| e.to_string())?;
+        chip8.quirks = self.quirks;
+        chip8.mem.address_mode = self.address_mode;
+        chip8.mem.set_font(self.font);
+        chip8.stack_depth = self.stack_depth;
+        chip8.rng = Rng::new(self.seed);
+        chip8.paused = false;
+
+        Ok(chip8)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+
+        Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} {}\n", MAGIC, MOVIE_VERSION);
+
+        let mut quirks: Vec<&str> = quirk_flags(&mut self.quirks.clone())
+            .into_iter()
+            .filter(|(_, on)| **on)
+            .map(|(name, _)| name)
+            .collect();
+        quirks.extend(
+            INDEX_INCREMENTS
+                .iter()
+                .filter(|(_, increment)| *increment == self.quirks.load_store_increments_i)
+                .map(|(name, _)| *name),
+        );
+        let memory = match self.address_mode {
+            AddressMode::Wrap => "wrap",
+            AddressMode::Fault => "fault",
+        };
+
+        writeln!(text, "rom {:016X}", self.rom_hash).unwrap();
+        writeln!(text, "seed {}", self.seed).unwrap();
+        writeln!(text, "variant {}", self.variant.name()).unwrap();
+        writeln!(text, "font {}", self.font.name()).unwrap();
+        writeln!(text, "quirks {}", quirks.join(" ")).unwrap();
+        writeln!(text, "memory {}", memory).unwrap();
+        writeln!(text, "stack-depth {}", self.stack_depth).unwrap();
+        writeln!(text, "hz {}", self.cpu_hz).unwrap();
+
+        // Keys are held for many frames in a row, one line per change
+        text.push_str("frames\n");
+        let mut frames = self.frames.iter().peekable();
+        while let Some(&keys) = frames.next() {
+            let mut count = 1;
+            while frames.next_if_eq(&&keys).is_some() {
+                count += 1;
+            }
+            writeln!(text, "{:04X} {}", keys, count).unwrap();
+        }
+
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+
+        match lines.next().map(|(_, line)| line.split_once(' ')) {
+            Some(Some((MAGIC, version))) => {
+                if version.parse() != Ok(MOVIE_VERSION) {
+                    return Err(format!("unsupported movie version {}", version));
+                }
+            }
+            _ => return Err("not a movie".to_string()),
+        }
+
+        let mut settings = Settings(Vec::new());
+        for (n, line) in lines.by_ref() {
+            if line == "frames" {
+                break;
+            }
+
+            let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+            settings.0.push((n, name, value));
+        }
+
+        let (n, hash) = settings.get("rom")?;
+        let rom_hash = u64::from_str_radix(hash, 16)
+            .map_err(|_| format!("line {}: invalid ROM hash {}", n + 1, hash))?;
+
+        let mut quirks = Quirks {
+            shift_uses_vy: false,
+            load_store_increments_i: IndexIncrement::None,
+            jump_uses_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+            display_wait: false,
+        };
+        let (n, names) = settings.get("quirks")?;
+        for name in names.split_whitespace() {
+            let increment = INDEX_INCREMENTS.iter().find(|(q, _)| *q == name);
+            if let Some(&(_, increment)) = increment {
+                quirks.load_store_increments_i = increment;
+                continue;
+            }
+
+            match quirk_flags(&mut quirks)
+                .into_iter()
+                .find(|(q, _)| *q == name)
+            {
+                Some((_, on)) => *on = true,
+                None => return Err(format!("line {}: unknown quirk {}", n + 1, name)),
+            }
+        }
+
+        let mut frames = Vec::new();
+        for (n, line) in lines {
+            let error = || format!("line {}: expected keys and a frame count", n + 1);
+            let (keys, count) = line.split_once(' ').ok_or_else(error)?;
+            let keys = u16::from_str_radix(keys, 16).map_err(|_| error())?;
+            let count: usize = count.parse().map_err(|_| error())?;
+            if count > MAX_FRAMES - frames.len() {
+                return Err(format!(
+                    "line {}: movie longer than {} frames",
+                    n + 1,
+                    MAX_FRAMES
+                ));
+            }
+
+            frames.extend(std::iter::repeat_n(keys, count));
+        }
+
+        Ok(Movie {
+            rom_hash,
+            seed: settings.parsed("seed")?,
+            variant: settings.named("variant", &Variant::ALL)?,
+            font: settings.named("font", &Font::ALL)?,
+            quirks,
+            address_mode: settings.named(
+                "memory",
+                &[("wrap", AddressMode::Wrap), ("fault", AddressMode::Fault)],
+            )?,
+            stack_depth: settings.parsed("stack-depth")?,
+            cpu_hz: settings.parsed("hz")?,
+            frames,
+        })
+    }
+}
+
+/// The lines before `frames`, as line numbers, names and values.
+struct Settings<'a>(Vec<(usize, &'a str, &'a str)>);
+
+impl<'a> Settings<'a> {
+    fn get(&self, name: &str) -> Result<(usize, &'a str), String> {
+        self.0
+            .iter()
+            .find(|(_, setting, _)| *setting == name)
+            .map(|&(n, _, value)| (n, value))
+            .ok_or(format!("missing {}", name))
+    }
+
+    fn parsed<T: FromStr>(&self, name: &str) -> Result<T, String> {
+        let (n, value) = self.get(name)?;
+
+        value
+            .parse()
+            .map_err(|_| format!("line {}: invalid {} {}", n + 1, name, value))
+    }
+
+    /// The value among `values`, by name.
+    fn named<T: Copy>(&self, name: &str, values: &[(&str, T)]) -> Result<T, String> {
+        let (n, value) = self.get(name)?;
+
+        values
+            .iter()
+            .find(|(valid, _)| *valid == value)
+            .map(|&(_, named)| named)
+            .ok_or(format!("line {}: unknown {} {}", n + 1, name, value))
+    }
+}
+
+/// The quirks which are on or off by name, as written in movies.
+fn quirk_flags(quirks: &mut Quirks) -> [(&'static str, &mut bool); 5] {
+    [
+        ("shift_uses_vy", &mut quirks.shift_uses_vy),
+        ("jump_uses_vx", &mut quirks.jump_uses_vx),
+        ("vf_reset", &mut quirks.vf_reset),
+        ("clip_sprites", &mut quirks.clip_sprites),
+        ("display_wait", &mut quirks.display_wait),
+    ]
+}
+
+/// The increments of I by name, none is written as no name at all.
+const INDEX_INCREMENTS: [(&str, IndexIncrement); 2] = [
+    ("load_store_increments_i", IndexIncrement::XPlus1),
+    ("load_store_increments_i_by_x", IndexIncrement::X),
+];
+
+/// A movie being recorded or replayed, one frame at a time.
+///
+/// `frame` must be called at the start of every frame the machine runs,
+/// `Clock::frame` does it for its `playback`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Playback {
+    Recording {
+        movie: Movie,
+        /// Keys held by the player, the machine gets them at the start of
+        /// the next frame.
+        keys: [bool; 16],
+    },
+    Replaying {
+        movie: Movie,
+        /// Index of the next frame.
+        frame: usize,
+    },
+}
+
+impl Playback {
+    pub fn record(movie: Movie) -> Self {
+        Playback::Recording {
+            movie,
+            keys: [false; 16],
+        }
+    }
+
+    pub fn replay(movie: Movie) -> Self {
+        Playback::Replaying { movie, frame: 0 }
+    }
+
+    /// Sets the keys held by the player. They are recorded with the next
+    /// frame, and ignored while replaying.
+    pub fn hold(&mut self, held: [bool; 16]) {
+        if let Playback::Recording { keys, .. } = self {
+            *keys = held;
+        }
+    }
+
+    /// Gives `chip8` the keys of the frame about to run, and records them.
+    /// Once a replay is over every key is released.
+    pub fn frame(&mut self, chip8: &mut Chip8) {
+        match self {
+            Playback::Recording { movie, keys } => {
+                movie.frames.push(keys_to_bits(*keys));
+                chip8.set_keys(*keys);
+            }
+            Playback::Replaying { movie, frame } => {
+                let keys = movie.frames.get(*frame).copied().unwrap_or(0);
+                *frame = (*frame + 1).min(movie.frames.len());
+                chip8.set_keys(keys_from_bits(keys));
+            }
+        }
+    }
+
+    /// Whether a replay ran all of its frames.
+    pub fn finished(&self) -> bool {
+        match self {
+            Playback::Recording { .. } => false,
+            Playback::Replaying { movie, frame } => *frame == movie.frames.len(),
+        }
+    }
+
+    pub fn movie(&self) -> &Movie {
+        match self {
+            Playback::Recording { movie, .. } | Playback::Replaying { movie, .. } => movie,
+        }
+    }
+
+    pub fn into_movie(self) -> Movie {
+        match self {
+            Playback::Recording { movie, .. } | Playback::Replaying { movie, .. } => movie,
+        }
+    }
+}
+
+fn keys_to_bits(keys: [bool; 16]) -> u16 {
+    (0..16).fold(0, |bits, key| bits | (keys[key] as u16) << key)
+}
+
+fn keys_from_bits(bits: u16) -> [bool; 16] {
+    let mut keys = [false; 16];
+    for (key, pressed) in keys.iter_mut().enumerate() {
+        *pressed = bits & (1 << key) != 0;
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use crate::chip8::Chip8;
+    use crate::clock::Clock;
+    use crate::font::Font;
+    use crate::hash::fnv1a;
+    use crate::movie::{Movie, Playback};
+    use crate::quirks::Quirks;
+    use crate::variant::Variant;
+
+    // loop: RND V0, 0xFF; LD I, 0x300; LD [I], V0; DRW V0, V0, 1; SKNP V1;
+    // ADD V1, 1; JP loop
+    const ROM: [u8; 14] = [
+        0xC0, 0xFF, 0xA3, 0x00, 0xF0, 0x55, 0xD0, 0x01, 0xE1, 0xA1, 0x71, 0x01, 0x12, 0x00,
+    ];
+
+    /// Plays 30 frames holding key 1 during `held`, recording or replaying
+    /// `playback`.
+    fn play(mut chip8: Chip8, playback: Playback, held: Range<usize>) -> (Chip8, Playback) {
+        let mut clock = Clock::new(500);
+        clock.playback = Some(playback);
+
+        for n in 0..30 {
+            let mut keys = [false; 16];
+            keys[1] = held.contains(&n);
+            clock.playback.as_mut().unwrap().hold(keys);
+            clock.frame(&mut chip8).unwrap();
+        }
+
+        (chip8, clock.playback.unwrap())
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut chip8 = Chip8::from_rom_with_variant(&ROM, Variant::SuperChip).unwrap();
+        chip8.quirks = Quirks::CHIP_48;
+        chip8.mem.set_font(Font::Vip);
+        chip8.paused = false;
+        let movie = Movie::new(fnv1a(&ROM), &chip8, Font::Vip, 500);
+
+        let (recorded, playback) = play(chip8, Playback::record(movie), 10..20);
+        let movie = playback.into_movie();
+        assert_eq!(movie.frames.len(), 30);
+        assert_eq!(movie.frames[10], 0b10);
+
+        let movie = Movie::parse(&movie.to_text()).unwrap();
+        let chip8 = movie.start(&ROM).unwrap();
+        assert_eq!(
+            (chip8.variant, chip8.quirks),
+            (Variant::SuperChip, Quirks::CHIP_48)
+        );
+
+        // The keys held while replaying are ignored
+        let (replayed, playback) = play(chip8, Playback::replay(movie), 0..0);
+        assert!(playback.finished());
+        assert_eq!(replayed.save_state(), recorded.save_state());
+        assert_ne!(replayed.v[1], 0);
+    }
+
+    #[test]
+    fn test_movie_text() {
+        let chip8 = Chip8::from_rom(&ROM).unwrap();
+        let mut movie = Movie::new(fnv1a(&ROM), &chip8, Font::SuperChip, 700);
+        movie.seed = 42;
+        movie.frames = vec![0, 0, 0, 0x8001, 0x8001, 0];
+
+        let text = movie.to_text();
+        assert!(text.ends_with("frames\n0000 3\n8001 2\n0000 1\n"));
+        assert_eq!(Movie::parse(&text).unwrap(), movie);
+
+        assert!(Movie::parse("chipr-movie 2\n").is_err());
+        assert!(Movie::parse(&text.replace("hz 700", "hz fast")).is_err());
+        assert!(Movie::parse(&text.replace("vf_reset", "vf")).is_err());
+        assert!(Movie::parse(&text.replace("seed 42\n", "")).is_err());
+        assert!(Movie::parse(&text.replace("8001 2", "8001 18446744073709551615")).is_err());
+        assert!(movie.start(&[0x12, 0x00]).is_err());
+    }
+}
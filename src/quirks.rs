@@ -0,0 +1,108 @@
+/// Behaviors of the instructions which differ between CHIP-8 interpreters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8xy6` and `8xyE` shift Vy into Vx instead of shifting Vx in place.
+    pub shift_uses_vy: bool,
+    /// How far `Fx55` and `Fx65` move I.
+    pub load_store_increments_i: IndexIncrement,
+    /// `Bnnn` jumps to `nnn + Vx` (with x the high nibble of nnn) instead of
+    /// `nnn + V0`.
+    pub jump_uses_vx: bool,
+    /// `8xy1`, `8xy2` and `8xy3` reset VF to 0.
+    pub vf_reset: bool,
+    /// Sprites are clipped at the edges of the screen instead of wrapping
+    /// around. The starting position always wraps.
+    pub clip_sprites: bool,
+    /// `Dxyn` waits for the next 60 Hz frame, so at most one sprite is drawn
+    /// per frame.
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    pub const COSMAC_VIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: IndexIncrement::XPlus1,
+        jump_uses_vx: false,
+        vf_reset: true,
+        clip_sprites: true,
+        display_wait: true,
+    };
+
+    pub const CHIP_48: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: IndexIncrement::X,
+        jump_uses_vx: true,
+        vf_reset: false,
+        clip_sprites: true,
+        display_wait: false,
+    };
+
+    pub const SUPER_CHIP: Quirks = Quirks {
+        shift_uses_vy: false,
+        load_store_increments_i: IndexIncrement::None,
+        jump_uses_vx: true,
+        vf_reset: false,
+        clip_sprites: true,
+        display_wait: false,
+    };
+
+    /// As implemented by Octo.
+    pub const XO_CHIP: Quirks = Quirks {
+        shift_uses_vy: true,
+        load_store_increments_i: IndexIncrement::XPlus1,
+        jump_uses_vx: false,
+        vf_reset: false,
+        clip_sprites: false,
+        display_wait: false,
+    };
+
+    pub const PRESETS: [(&'static str, Quirks); 4] = [
+        ("COSMAC VIP", Quirks::COSMAC_VIP),
+        ("CHIP-48", Quirks::CHIP_48),
+        ("SUPER-CHIP", Quirks::SUPER_CHIP),
+        ("XO-CHIP", Quirks::XO_CHIP),
+    ];
+}
+
+/// How far `Fx55` and `Fx65` move I, given the last register x accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexIncrement {
+    /// I is left unchanged, as on SUPER-CHIP.
+    None,
+    /// I is incremented by x, as on CHIP-48.
+    X,
+    /// I is left pointing after the last register accessed, as on the COSMAC
+    /// VIP.
+    XPlus1,
+}
+
+impl IndexIncrement {
+    pub const ALL: [(&'static str, IndexIncrement); 3] = [
+        ("None", IndexIncrement::None),
+        ("x", IndexIncrement::X),
+        ("x + 1", IndexIncrement::XPlus1),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        IndexIncrement::ALL
+            .iter()
+            .find(|(_, increment)| increment == self)
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+
+    /// The increment after accessing V0 to Vx.
+    pub fn amount(&self, x: usize) -> u16 {
+        match self {
+            IndexIncrement::None => 0,
+            IndexIncrement::X => x as u16,
+            IndexIncrement::XPlus1 => x as u16 + 1,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks::COSMAC_VIP
+    }
+}
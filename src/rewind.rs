@@ -0,0 +1,316 @@
+use std::collections::VecDeque;
+
+use crate::chip8::Chip8;
+use crate::error::StateError;
+
+/// Ring buffer of recent machine states, newest last.
+///
+/// Only the newest state is kept whole. Older ones are stored as the
+/// difference with the state after them, which is small as most of the
+/// memory does not change from one frame to the next.
+pub struct Rewind {
+    /// Number of states kept, older ones are dropped.
+    pub capacity: usize,
+    newest: Option<Vec<u8>>,
+    /// Turn a state into the one before it, oldest first.
+    deltas: VecDeque<Delta>,
+}
+
+enum Delta {
+    /// Run-length encoded XOR with the next state, when both have the same
+    /// size.
+    Xor(Vec<u8>),
+    /// The whole previous state, after a switch between lo-res and hi-res.
+    Full(Vec<u8>),
+}
+
+impl Rewind {
+    pub fn new(capacity: usize) -> Self {
+        Rewind {
+            capacity,
+            newest: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.newest.iter().count() + self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+    }
+
+    /// Memory used by the stored states.
+    pub fn size_in_bytes(&self) -> usize {
+        let deltas: usize = self
+            .deltas
+            .iter()
+            .map(|delta| match delta {
+                Delta::Xor(bytes) | Delta::Full(bytes) => bytes.len(),
+            })
+            .sum();
+
+        self.newest.as_ref().map_or(0, Vec::len) + deltas
+    }
+
+    /// Records the current state of `chip8`.
+    ///
+    /// States are meant to be pushed at the start of frames, after the keys
+    /// are set, so that `step_back` replays instructions with the keys they
+    /// saw.
+    pub fn push(&mut self, chip8: &Chip8) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let state = chip8.save_state();
+
+        if let Some(previous) = self.newest.take() {
+            let delta = if previous.len() == state.len() {
+                Delta::Xor(encode_xor(&previous, &state))
+            } else {
+                Delta::Full(previous)
+            };
+            self.deltas.push_back(delta);
+        }
+        self.newest = Some(state);
+
+        while self.len() > self.capacity {
+            self.deltas.pop_front();
+        }
+    }
+
+    /// Removes and returns the newest state.
+    pub fn pop(&mut self) -> Result<Option<Chip8>, StateError> {
+        let Some(state) = self.newest.take() else {
+            return Ok(None);
+        };
+
+        self.newest = self.deltas.pop_back().map(|delta| match delta {
+            Delta::Xor(delta) => decode_xor(&state, &delta),
+            Delta::Full(previous) => previous,
+        });
+
+        Chip8::from_state(&state).map(Some)
+    }
+
+    /// Returns `chip8` as it was one instruction earlier, or `None` if that
+    /// is older than every stored state or does not replay.
+    ///
+    /// The newest state at or before that point is restored and run forward,
+    /// so each step back replays at most one frame of instructions. The
+    /// states newer than the result are dropped.
+    pub fn step_back(&mut self, chip8: &Chip8) -> Result<Option<Chip8>, StateError> {
+        let Some(target) = chip8.cycles.checked_sub(1) else {
+            return Ok(None);
+        };
+
+        let mut base = loop {
+            match self.pop()? {
+                Some(state) if state.cycles <= target => break state,
+                Some(_) => {}
+                None => return Ok(None),
+            }
+        };
+
+        // Keep the base around for the next step back
+        self.push(&base);
+
+        while base.cycles < target {
+            if base.execute().is_err() {
+                return Ok(None);
+            }
+        }
+        base.paused = true;
+
+        Ok(Some(base))
+    }
+}
+
+/// Encodes `a ^ b` as runs of `(zeros, length, bytes)`, with the counts
+/// stored as LEB128.
+fn encode_xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+
+    while pos < a.len() {
+        let zeros = (pos..a.len()).take_while(|&i| a[i] == b[i]).count();
+        let start = pos + zeros;
+        let len = (start..a.len()).take_while(|&i| a[i] != b[i]).count();
+
+        write_leb128(&mut out, zeros);
+        write_leb128(&mut out, len);
+        out.extend((start..start + len).map(|i| a[i] ^ b[i]));
+
+        pos = start + len;
+    }
+
+    out
+}
+
+/// Applies a delta from `encode_xor` to either of its inputs to get the other.
+fn decode_xor(state: &[u8], delta: &[u8]) -> Vec<u8> {
+    let mut out = state.to_vec();
+    let (mut pos, mut i) = (0, 0);
+
+    while i < delta.len() {
+        let zeros = read_leb128(delta, &mut i);
+        let len = read_leb128(delta, &mut i);
+
+        pos += zeros;
+        for byte in &mut out[pos..pos + len] {
+            *byte ^= delta[i];
+            i += 1;
+        }
+        pos += len;
+    }
+
+    out
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_leb128(data: &[u8], i: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+
+    loop {
+        let byte = data[*i];
+        *i += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            return value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::rewind::Rewind;
+
+    // LD V0, 0; loop: ADD V0, 1; LD I, 0x300; LD [I], V0; JP loop
+    const COUNTER: [u8; 10] = [0x60, 0x00, 0x70, 0x01, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x02];
+
+    #[test]
+    fn test_rewind_pops_in_reverse() {
+        let mut chip8 = Chip8::from_rom(&COUNTER).unwrap();
+        let mut rewind = Rewind::new(3);
+        let rng = chip8.rng;
+
+        for _ in 0..5 {
+            chip8.execute().unwrap();
+            rewind.push(&chip8);
+        }
+        assert_eq!(rewind.len(), 3);
+
+        // A state is mostly memory, the deltas only hold what changed
+        assert!(rewind.size_in_bytes() < chip8.save_state().len() + 100);
+
+        assert_eq!(rewind.pop().unwrap().unwrap().cycles, 5);
+        assert_eq!(rewind.pop().unwrap().unwrap().save_state(), {
+            let mut chip8 = Chip8::from_rom(&COUNTER).unwrap();
+            chip8.rng = rng;
+            chip8.run_to(4);
+            chip8.save_state()
+        });
+        assert_eq!(rewind.pop().unwrap().unwrap().cycles, 3);
+        assert!(rewind.pop().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rewind_across_resolution_change() {
+        let mut chip8 = Chip8::from_rom(&COUNTER).unwrap();
+        let mut rewind = Rewind::new(10);
+
+        rewind.push(&chip8);
+        chip8.set_hires(true);
+        rewind.push(&chip8);
+        chip8.set_hires(false);
+        rewind.push(&chip8);
+
+        assert!(!rewind.pop().unwrap().unwrap().hires);
+        assert!(rewind.pop().unwrap().unwrap().hires);
+        assert!(!rewind.pop().unwrap().unwrap().hires);
+    }
+
+    #[test]
+    fn test_step_back() {
+        let mut chip8 = Chip8::from_rom(&COUNTER).unwrap();
+        let mut rewind = Rewind::new(10);
+
+        // One state every 7 instructions, like frames
+        rewind.push(&chip8);
+        for cycle in 1..=20 {
+            chip8.execute().unwrap();
+            if cycle % 7 == 0 {
+                rewind.push(&chip8);
+            }
+        }
+
+        for cycles in (0..20).rev() {
+            chip8 = rewind.step_back(&chip8).unwrap().unwrap();
+
+            let mut expected = Chip8::from_rom(&COUNTER).unwrap();
+            expected.run_to(cycles);
+            assert_eq!(chip8.cycles, cycles);
+            assert_eq!(chip8.mem.get(0x300), expected.mem.get(0x300));
+            assert_eq!(chip8.v, expected.v);
+        }
+
+        assert!(rewind.step_back(&chip8).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_step_back_across_key_change() {
+        // LD V0, 0; loop: SKNP V0; ADD V1, 1; JP loop
+        let rom = [0x60, 0x00, 0xE0, 0xA1, 0x71, 0x01, 0x12, 0x02];
+        let mut chip8 = Chip8::from_rom(&rom).unwrap();
+        let mut rewind = Rewind::new(10);
+
+        // V1 after each number of cycles, with key 0 held from the third
+        // frame on
+        let mut counts = vec![chip8.v[1]];
+        for frame in 0..4 {
+            chip8.set_key(0, frame >= 2);
+            rewind.push(&chip8);
+            for _ in 0..7 {
+                chip8.execute().unwrap();
+                counts.push(chip8.v[1]);
+            }
+        }
+        assert!(counts[14] < counts[28]);
+
+        for cycles in (0..28).rev() {
+            chip8 = rewind.step_back(&chip8).unwrap().unwrap();
+            assert_eq!(chip8.v[1], counts[cycles as usize]);
+        }
+    }
+
+    impl Chip8 {
+        fn run_to(&mut self, cycles: u64) {
+            while self.cycles < cycles {
+                self.execute().unwrap();
+            }
+        }
+    }
+}
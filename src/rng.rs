@@ -0,0 +1,54 @@
+/// SplitMix64, the random number generator of `Cxkk`.
+///
+/// Its whole state is one integer, which save states keep, so a machine
+/// started from the same seed draws the same numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng {
+    /// The state the generator started from.
+    pub seed: u64,
+    pub state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng { seed, state: seed }
+    }
+
+    /// A generator with a seed from the operating system.
+    pub fn from_entropy() -> Self {
+        Rng::new(rand::random())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        // The high bits are the best mixed
+        (self.next_u64() >> 56) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rng::Rng;
+
+    #[test]
+    fn test_rng_sequence() {
+        let mut rng = Rng::new(1234567);
+        assert_eq!(rng.next_u64(), 0x599E_D017_FB08_FC85);
+        assert_eq!(rng.seed, 1234567);
+
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let a: Vec<u8> = (0..100).map(|_| a.next_u8()).collect();
+        let b: Vec<u8> = (0..100).map(|_| b.next_u8()).collect();
+        assert_eq!(a, b);
+        assert!(a.iter().any(|&n| n != a[0]));
+    }
+}
@@ -0,0 +1,462 @@
+use crate::chip8::{Chip8, HEIGHT, HIRES_HEIGHT, HIRES_WIDTH, WIDTH};
+use crate::error::StateError;
+use crate::mem::{AddressMode, Mem};
+use crate::quirks::{IndexIncrement, Quirks};
+use crate::variant::Variant;
+
+const MAGIC: &[u8; 8] = b"CHIPRSAV";
+/// Bumped whenever the layout below changes, older states are rejected.
+pub const STATE_VERSION: u16 = 1;
+
+impl Chip8 {
+    /// Serializes the whole machine, to be restored with `from_state`.
+    ///
+    /// The layout is a header (magic and `STATE_VERSION`) followed by every
+    /// field in a fixed order, integers are little endian.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = Writer(Vec::with_capacity(
+            self.mem.ram.len() + self.gfx.len() + 128,
+        ));
+
+        w.bytes(MAGIC);
+        w.u16(STATE_VERSION);
+
+        w.u8(match self.variant {
+            Variant::Chip8 => 0,
+            Variant::SuperChip => 1,
+            Variant::XoChip => 2,
+        });
+        w.u8(match self.mem.address_mode {
+            AddressMode::Wrap => 0,
+            AddressMode::Fault => 1,
+        });
+        w.u8(quirks_to_bits(self.quirks));
+
+        w.bytes(&self.v);
+        w.u16(self.i);
+        w.u8(self.delay_timer);
+        w.u8(self.sound_timer);
+        w.u16(self.pc);
+
+        w.u16(self.stack_depth as u16);
+        w.u16(self.stack.len() as u16);
+        for &ret in &self.stack {
+            w.u16(ret);
+        }
+
+        w.u32(self.mem.ram.len() as u32);
+        w.bytes(&self.mem.ram);
+
+        let keys = (0..16).fold(0, |bits, key| bits | (self.keys[key] as u16) << key);
+        w.u16(keys);
+
+        w.bool(self.hires);
+        w.bytes(&self.gfx);
+
+        w.bool(self.paused);
+        w.u8(self.target_register.map_or(0xFF, |x| x as u8));
+        w.u8(self.pressed_key.unwrap_or(0xFF));
+        w.u8(self.released_key.unwrap_or(0xFF));
+        w.bool(self.vblank);
+
+        w.bytes(&self.rpl);
+        w.u8(self.planes);
+        w.bool(self.pattern.is_some());
+        w.bytes(&self.pattern.unwrap_or([0; 16]));
+        w.u8(self.pitch);
+
+        w.u64(self.cycles);
+
+        w.u64(self.rng.seed);
+        w.u64(self.rng.state);
+
+        w.bool(self.rom_hash.is_some());
+        w.u64(self.rom_hash.unwrap_or(0));
+
+        w.0
+    }
+
+    /// Restores a machine serialized by `save_state`.
+    ///
+    /// Debugging state is not saved: there is no trace, no last draw and no
+    /// write times, see `carry_over`.
+    pub fn from_state(data: &[u8]) -> Result<Self, StateError> {
+        let mut r = Reader { data, pos: 0 };
+
+        if r.bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err(StateError::NotASaveState);
+        }
+
+        let version = r.u16()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let variant = match r.u8()? {
+            0 => Variant::Chip8,
+            1 => Variant::SuperChip,
+            2 => Variant::XoChip,
+            _ => return Err(StateError::Invalid),
+        };
+        let address_mode = match r.u8()? {
+            0 => AddressMode::Wrap,
+            1 => AddressMode::Fault,
+            _ => return Err(StateError::Invalid),
+        };
+        let quirks = quirks_from_bits(r.u8()?)?;
+
+        let mut v = [0; 16];
+        v.copy_from_slice(r.bytes(16)?);
+        let i = r.u16()?;
+        let delay_timer = r.u8()?;
+        let sound_timer = r.u8()?;
+        let pc = r.u16()?;
+
+        let stack_depth = r.u16()? as usize;
+        let stack = (0..r.u16()?)
+            .map(|_| r.u16())
+            .collect::<Result<Vec<u16>, StateError>>()?;
+        if stack_depth == 0 || stack.len() > stack_depth {
+            return Err(StateError::Invalid);
+        }
+
+        // The size is checked before allocating, it could be anything
+        let ram_size = r.u32()? as usize;
+        let ram = r.bytes(ram_size)?;
+        if ram_size != variant.ram_size() {
+            return Err(StateError::Invalid);
+        }
+        let mut mem = Mem::with_size(ram_size);
+        mem.ram.copy_from_slice(ram);
+        mem.address_mode = address_mode;
+
+        let mut chip8 = Chip8::new(mem);
+        chip8.variant = variant;
+        chip8.quirks = quirks;
+        chip8.v = v;
+        chip8.i = i;
+        chip8.delay_timer = delay_timer;
+        chip8.sound_timer = sound_timer;
+        chip8.pc = pc;
+        chip8.stack_depth = stack_depth;
+        chip8.stack = stack;
+
+        let keys = r.u16()?;
+        for key in 0..16 {
+            chip8.keys[key] = keys & (1 << key) != 0;
+        }
+
+        chip8.hires = r.bool()?;
+        let len = if chip8.hires {
+            HIRES_WIDTH * HIRES_HEIGHT
+        } else {
+            WIDTH * HEIGHT
+        };
+        chip8.gfx = r.bytes(len)?.to_vec();
+
+        chip8.paused = r.bool()?;
+        chip8.target_register = r.optional_u8()?.map(|x| x as usize & 0xF);
+        chip8.pressed_key = r.optional_u8()?;
+        chip8.released_key = r.optional_u8()?;
+        chip8.vblank = r.bool()?;
+
+        chip8.rpl.copy_from_slice(r.bytes(16)?);
+        chip8.planes = r.u8()?;
+        let has_pattern = r.bool()?;
+        let mut pattern = [0; 16];
+        pattern.copy_from_slice(r.bytes(16)?);
+        chip8.pattern = if has_pattern { Some(pattern) } else { None };
+        chip8.pitch = r.u8()?;
+
+        chip8.cycles = r.u64()?;
+        chip8.rng.seed = r.u64()?;
+        chip8.rng.state = r.u64()?;
+
+        let has_rom_hash = r.bool()?;
+        let rom_hash = r.u64()?;
+        chip8.rom_hash = if has_rom_hash { Some(rom_hash) } else { None };
+
+        if r.pos != data.len() {
+            return Err(StateError::Invalid);
+        }
+
+        Ok(chip8)
+    }
+}
+
+/// Bits 0 to 4 are the quirks which are on or off, bits 5 and 6 the
+/// increment of I.
+fn quirks_to_bits(quirks: Quirks) -> u8 {
+    let flags = [
+        quirks.shift_uses_vy,
+        quirks.jump_uses_vx,
+        quirks.vf_reset,
+        quirks.clip_sprites,
+        quirks.display_wait,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (n, &on)| bits | (on as u8) << n);
+
+    let increment = match quirks.load_store_increments_i {
+        IndexIncrement::None => 0,
+        IndexIncrement::X => 1,
+        IndexIncrement::XPlus1 => 2,
+    };
+
+    flags | increment << 5
+}
+
+fn quirks_from_bits(bits: u8) -> Result<Quirks, StateError> {
+    let on = |n: u8| bits & (1 << n) != 0;
+
+    Ok(Quirks {
+        shift_uses_vy: on(0),
+        load_store_increments_i: match bits >> 5 {
+            0 => IndexIncrement::None,
+            1 => IndexIncrement::X,
+            2 => IndexIncrement::XPlus1,
+            _ => return Err(StateError::Invalid),
+        },
+        jump_uses_vx: on(1),
+        vf_reset: on(2),
+        clip_sprites: on(3),
+        display_wait: on(4),
+    })
+}
+
+impl Chip8 {
+    /// Takes the debugging state which save states do not hold from
+    /// `later`, the same run at a later point: the trace, the last draw and
+    /// the write times, dropping what happened after this point.
+    pub fn carry_over(&mut self, later: &mut Chip8) {
+        let cycles = self.cycles;
+
+        self.trace = later.trace.take();
+        if let Some(tracer) = &mut self.trace {
+            tracer.recent.retain(|entry| entry.cycle < cycles);
+        }
+
+        if self.last_draw.is_none() {
+            self.last_draw = later.last_draw.filter(|draw| draw.cycle < cycles);
+        }
+
+        if self.mem.written_at.len() == later.mem.written_at.len() {
+            for (written, &later) in self.mem.written_at.iter_mut().zip(&later.mem.written_at) {
+                if *written == 0 && later <= cycles {
+                    *written = later;
+                }
+            }
+        }
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.0.push(value as u8);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], StateError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or(StateError::Truncated)?;
+        self.pos += n;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid),
+        }
+    }
+
+    /// A byte where 0xFF stands for `None`.
+    fn optional_u8(&mut self) -> Result<Option<u8>, StateError> {
+        Ok(Some(self.u8()?).filter(|&value| value != 0xFF))
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes([self.u8()?, self.u8()?]))
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(4)?);
+
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, StateError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8;
+    use crate::error::StateError;
+    use crate::hash::fnv1a;
+    use crate::quirks::Quirks;
+    use crate::state::{quirks_from_bits, quirks_to_bits, STATE_VERSION};
+    use crate::trace::Tracer;
+    use crate::variant::Variant;
+
+    #[test]
+    fn test_state_roundtrip() {
+        // LD V1, 0x42; CALL 0x206; LD V2, K
+        let rom = [0x61, 0x42, 0x22, 0x06, 0x00, 0x00, 0xF2, 0x0A];
+        let mut chip8 = Chip8::from_rom_with_variant(&rom, Variant::XoChip).unwrap();
+        chip8.quirks = Quirks::XO_CHIP;
+        chip8.set_hires(true);
+        chip8.gfx[100] = 3;
+        chip8.pattern = Some([0xF0; 16]);
+        chip8.paused = false;
+        chip8.run(3).unwrap();
+        chip8.set_key(0xC, true);
+
+        let state = chip8.save_state();
+        let restored = Chip8::from_state(&state).unwrap();
+
+        assert_eq!(restored.save_state(), state);
+        assert_eq!(restored.v[1], 0x42);
+        assert_eq!(restored.stack, [0x204]);
+        assert_eq!(restored.target_register, Some(2));
+        assert_eq!(restored.pressed_key, Some(0xC));
+        assert_eq!(restored.gfx[100], 3);
+        assert_eq!(restored.mem.ram.len(), 0x10000);
+        assert_eq!(restored.quirks, Quirks::XO_CHIP);
+        assert_eq!(restored.cycles, 3);
+        assert_eq!(restored.rng, chip8.rng);
+        assert_eq!(restored.rom_hash, Some(fnv1a(&rom)));
+
+        for (_, quirks) in Quirks::PRESETS {
+            assert_eq!(quirks_from_bits(quirks_to_bits(quirks)), Ok(quirks));
+        }
+    }
+
+    #[test]
+    fn test_carry_over() {
+        // I = 0x300, LD V0, 5; LD [I], V0; DRW V0, V0, 1; JP 0x208
+        let rom = [0xA3, 0x00, 0x60, 0x05, 0xF0, 0x55, 0xD0, 0x01, 0x12, 0x08];
+        let mut chip8 = Chip8::from_rom(&rom).unwrap();
+        chip8.quirks.display_wait = false;
+        chip8.trace = Some(Tracer::new());
+
+        let mut states = Vec::new();
+        for _ in 0..5 {
+            states.push(chip8.save_state());
+            chip8.execute().unwrap();
+        }
+
+        // After the write and the draw
+        let mut later = Chip8::from_state(&states[4]).unwrap();
+        later.carry_over(&mut chip8);
+        assert_eq!(later.trace.as_ref().unwrap().recent.len(), 4);
+        assert_eq!(later.last_draw.unwrap().cycle, 3);
+        assert_eq!(later.mem.written_at[0x300], 3);
+
+        // Before them
+        let mut earlier = Chip8::from_state(&states[2]).unwrap();
+        earlier.carry_over(&mut later);
+        assert_eq!(earlier.trace.as_ref().unwrap().recent.len(), 2);
+        assert_eq!(earlier.last_draw, None);
+        assert_eq!(earlier.mem.written_at[0x300], 0);
+    }
+
+    #[test]
+    fn test_state_errors() {
+        let state = Chip8::from_rom(&[0x12, 0x00]).unwrap().save_state();
+
+        assert!(matches!(
+            Chip8::from_state(b"CHIP8"),
+            Err(StateError::NotASaveState)
+        ));
+        assert!(matches!(
+            Chip8::from_state(&state[..state.len() - 1]),
+            Err(StateError::Truncated)
+        ));
+
+        // Memory sizes are those of the variant, whatever the file says
+        let mut huge = state.clone();
+        huge[39..43].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        assert!(matches!(
+            Chip8::from_state(&huge),
+            Err(StateError::Truncated)
+        ));
+
+        let mut xo = Chip8::from_rom_with_variant(&[0x12, 0x00], Variant::XoChip)
+            .unwrap()
+            .save_state();
+        xo[10] = 0;
+        assert!(matches!(Chip8::from_state(&xo), Err(StateError::Invalid)));
+
+        // The stack is within its depth, which is at least 1
+        let mut calls = Chip8::from_rom(&[0x22, 0x02, 0x22, 0x04]).unwrap();
+        calls.execute().unwrap();
+        calls.execute().unwrap();
+        let calls = calls.save_state();
+        for depth in [0u16, 1] {
+            let mut shallow = calls.clone();
+            shallow[35..37].copy_from_slice(&depth.to_le_bytes());
+            assert!(matches!(
+                Chip8::from_state(&shallow),
+                Err(StateError::Invalid)
+            ));
+        }
+
+        // No increment of I is numbered 3
+        let mut quirks = state.clone();
+        quirks[12] |= 0b0110_0000;
+        assert!(matches!(
+            Chip8::from_state(&quirks),
+            Err(StateError::Invalid)
+        ));
+
+        let mut newer = state.clone();
+        newer[8..10].copy_from_slice(&(STATE_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Chip8::from_state(&newer),
+            Err(StateError::UnsupportedVersion(v)) if v == STATE_VERSION + 1
+        ));
+    }
+}
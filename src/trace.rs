@@ -0,0 +1,767 @@
+//! Execution traces, to compare runs with other emulators.
+//!
+//! Each instruction executed becomes an `Entry`, written as one line in
+//! either format:
+//!
+//! - Text: `<cycle> <pc> <opcode> v=<V0..VF> i=<I> sp=<depth> -> v=... i=...
+//!   sp=... mem=<writes> gfx=<hash> ; <mnemonic>`, where the registers are
+//!   before then after the instruction. Numbers are hex except `cycle` and
+//!   `sp`, `mem` lists the bytes written as `addr:value` separated by commas
+//!   (`-` if none) and `gfx` is the FNV-1a hash of the framebuffer after the
+//!   instruction.
+//! - JSON lines: `{"cycle":0,"pc":512,"opcode":24576,"mnemonic":"LD V0, 0x00",
+//!   "before":{"v":[0,...],"i":0,"sp":0},"after":{...},"writes":[[768,1]],
+//!   "gfx":"<hash>"}` with the same fields, numbers in decimal and the hash
+//!   as a hex string.
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::chip8::Chip8;
+use crate::disasm::decode;
+use crate::hash::fnv1a;
+use crate::instr::bytes_to_word;
+
+/// Entries kept by `Tracer::recent` by default.
+pub const RECENT: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+/// The registers of a trace entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Registers {
+    pub v: [u8; 16],
+    pub i: u16,
+    /// Depth of the stack.
+    pub sp: usize,
+}
+
+impl Registers {
+    pub fn of(chip8: &Chip8) -> Self {
+        Registers {
+            v: chip8.v,
+            i: chip8.i,
+            sp: chip8.stack.len(),
+        }
+    }
+}
+
+/// One executed instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// `Chip8::cycles` before the instruction ran.
+    pub cycle: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub mnemonic: String,
+    pub before: Registers,
+    pub after: Registers,
+    /// Bytes written to memory, as addresses and values.
+    pub writes: Vec<(usize, u8)>,
+    /// FNV-1a hash of the framebuffer after the instruction.
+    pub gfx: u64,
+}
+
+impl Entry {
+    /// Parses a line in either format.
+    pub fn parse(line: &str) -> Result<Entry, String> {
+        if line.trim_start().starts_with('{') {
+            parse_json(line)
+        } else {
+            parse_text(line)
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let registers = |r: &Registers| {
+            let v: Vec<String> = r.v.iter().map(|x| x.to_string()).collect();
+            format!(r#"{{"v":[{}],"i":{},"sp":{}}}"#, v.join(","), r.i, r.sp)
+        };
+        let writes: Vec<String> = self
+            .writes
+            .iter()
+            .map(|(addr, value)| format!("[{},{}]", addr, value))
+            .collect();
+        let mnemonic = self.mnemonic.replace('\\', "\\\\").replace('"', "\\\"");
+
+        format!(
+            r#"{{"cycle":{},"pc":{},"opcode":{},"mnemonic":"{}","before":{},"after":{},"writes":[{}],"gfx":"{:016X}"}}"#,
+            self.cycle,
+            self.pc,
+            self.opcode,
+            mnemonic,
+            registers(&self.before),
+            registers(&self.after),
+            writes.join(","),
+            self.gfx
+        )
+    }
+}
+
+/// The text format.
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let registers = |r: &Registers| {
+            let v: String = r.v.iter().map(|x| format!("{:02X}", x)).collect();
+            format!("v={} i={:04X} sp={}", v, r.i, r.sp)
+        };
+        write!(
+            f,
+            "{} {:04X} {:04X} {} -> {} mem={} gfx={:016X} ; {}",
+            self.cycle,
+            self.pc,
+            self.opcode,
+            registers(&self.before),
+            registers(&self.after),
+            text_writes(&self.writes),
+            self.gfx,
+            self.mnemonic
+        )
+    }
+}
+
+/// Writes as `addr:value` separated by commas, or `-` if none.
+fn text_writes(writes: &[(usize, u8)]) -> String {
+    if writes.is_empty() {
+        return "-".to_string();
+    }
+
+    let writes: Vec<String> = writes
+        .iter()
+        .map(|(addr, value)| format!("{:04X}:{:02X}", addr, value))
+        .collect();
+    writes.join(",")
+}
+
+/// The first entries two traces disagree on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the entries in both traces.
+    pub index: usize,
+    /// What differs, e.g. `V3 after: 05 != 06`.
+    pub differences: Vec<String>,
+}
+
+/// Compares two traces entry by entry, ignoring the mnemonics. A trace
+/// ending before the other diverges where it ends.
+pub fn diverge(a: &[Entry], b: &[Entry]) -> Option<Divergence> {
+    for (index, (a, b)) in a.iter().zip(b).enumerate() {
+        let differences = differences(a, b);
+
+        if !differences.is_empty() {
+            return Some(Divergence { index, differences });
+        }
+    }
+
+    let index = a.len().min(b.len());
+    let ended = match a.len().cmp(&b.len()) {
+        Ordering::Less => "first trace ended",
+        Ordering::Greater => "second trace ended",
+        Ordering::Equal => return None,
+    };
+
+    Some(Divergence {
+        index,
+        differences: vec![ended.to_string()],
+    })
+}
+
+fn differences(a: &Entry, b: &Entry) -> Vec<String> {
+    let mut differences = Vec::new();
+    let mut compare = |name: String, a: String, b: String| {
+        if a != b {
+            differences.push(format!("{}: {} != {}", name, a, b));
+        }
+    };
+
+    compare(
+        "cycle".to_string(),
+        a.cycle.to_string(),
+        b.cycle.to_string(),
+    );
+    compare(
+        "pc".to_string(),
+        format!("{:04X}", a.pc),
+        format!("{:04X}", b.pc),
+    );
+    compare(
+        "opcode".to_string(),
+        format!("{:04X}", a.opcode),
+        format!("{:04X}", b.opcode),
+    );
+
+    for (when, a, b) in [("before", a.before, b.before), ("after", a.after, b.after)] {
+        for x in 0..16 {
+            compare(
+                format!("V{:X} {}", x, when),
+                format!("{:02X}", a.v[x]),
+                format!("{:02X}", b.v[x]),
+            );
+        }
+        compare(
+            format!("I {}", when),
+            format!("{:04X}", a.i),
+            format!("{:04X}", b.i),
+        );
+        compare(
+            format!("stack depth {}", when),
+            a.sp.to_string(),
+            b.sp.to_string(),
+        );
+    }
+
+    compare(
+        "memory writes".to_string(),
+        text_writes(&a.writes),
+        text_writes(&b.writes),
+    );
+    compare(
+        "framebuffer hash".to_string(),
+        format!("{:016X}", a.gfx),
+        format!("{:016X}", b.gfx),
+    );
+
+    differences
+}
+
+/// Which instructions are traced.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    /// Only the instructions between these addresses (inclusive).
+    pub pc: Option<(u16, u16)>,
+    /// Only the opcodes matching one of these masks and values, see
+    /// `breakpoint::opcode_pattern`. Every opcode if empty.
+    pub opcodes: Vec<(u16, u16)>,
+}
+
+impl Filter {
+    pub fn matches(&self, pc: u16, opcode: u16) -> bool {
+        let pc_matches = self
+            .pc
+            .is_none_or(|(start, end)| (start..=end).contains(&pc));
+        let opcode_matches = self.opcodes.is_empty()
+            || self
+                .opcodes
+                .iter()
+                .any(|&(mask, value)| opcode & mask == value);
+
+        pc_matches && opcode_matches
+    }
+}
+
+/// Records the instructions executed, see `Chip8::trace`.
+pub struct Tracer {
+    pub filter: Filter,
+    pub format: Format,
+    out: Option<Box<dyn Write>>,
+    /// The last entries, oldest first.
+    pub recent: VecDeque<Entry>,
+    pub capacity: usize,
+    /// First error writing to the output, which stops the logging.
+    pub error: Option<io::Error>,
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Tracer::new()
+    }
+}
+
+impl Tracer {
+    /// A tracer which only keeps the last `RECENT` entries in memory.
+    pub fn new() -> Self {
+        Tracer {
+            filter: Filter::default(),
+            format: Format::Text,
+            out: None,
+            recent: VecDeque::with_capacity(RECENT),
+            capacity: RECENT,
+            error: None,
+        }
+    }
+
+    /// A tracer which also writes every entry to `out`.
+    pub fn with_output(out: Box<dyn Write>, format: Format) -> Self {
+        Tracer {
+            format,
+            out: Some(out),
+            ..Tracer::new()
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.out {
+            Some(out) => out.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn record(&mut self, entry: Entry) {
+        if let Some(out) = &mut self.out {
+            let result = match self.format {
+                Format::Text => writeln!(out, "{}", entry),
+                Format::Json => writeln!(out, "{}", entry.to_json()),
+            };
+
+            if let Err(e) = result {
+                self.error = Some(e);
+                self.out = None;
+            }
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+        if self.recent.len() == self.capacity {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(entry);
+    }
+}
+
+impl Chip8 {
+    /// The entry of the instruction at `pc`, if it is traced, filled in as
+    /// far as it can be before the instruction runs.
+    ///
+    /// The instruction is decoded here since it may overwrite itself.
+    pub(crate) fn trace_before(&self) -> Option<Entry> {
+        let tracer = self.trace.as_ref()?;
+        let opcode = bytes_to_word(
+            self.mem.get(self.pc).ok()?,
+            self.mem.get(self.pc.wrapping_add(1)).ok()?,
+        );
+        if !tracer.filter.matches(self.pc, opcode) {
+            return None;
+        }
+
+        let (opcode, mnemonic) = match decode(&self.mem.ram, self.pc as usize, self.variant) {
+            Some(instr) => (instr.opcode, instr.text),
+            None => (opcode, "?".to_string()),
+        };
+        let before = Registers::of(self);
+
+        Some(Entry {
+            cycle: self.cycles,
+            pc: self.pc,
+            opcode,
+            mnemonic,
+            before,
+            after: before,
+            writes: Vec::new(),
+            gfx: 0,
+        })
+    }
+
+    /// Completes and records the entry of the instruction which just ran.
+    pub(crate) fn trace_after(&mut self, mut entry: Entry) {
+        entry.after = Registers::of(self);
+        entry.writes = self
+            .mem
+            .writes
+            .iter()
+            .map(|&addr| (addr, self.mem.ram[addr]))
+            .collect();
+        entry.gfx = fnv1a(&self.gfx);
+
+        if let Some(tracer) = &mut self.trace {
+            tracer.record(entry);
+        }
+    }
+}
+
+fn parse_text(line: &str) -> Result<Entry, String> {
+    let (fields, mnemonic) = line
+        .split_once(" ; ")
+        .ok_or_else(|| "missing mnemonic".to_string())?;
+    let words: Vec<&str> = fields.split(' ').collect();
+    let [cycle, pc, opcode, before @ .., arrow, _, _, _, mem, gfx] = &words[..] else {
+        return Err("truncated entry".to_string());
+    };
+    if *arrow != "->" || before.len() != 3 {
+        return Err("expected registers before and after ->".to_string());
+    }
+
+    let writes = match field(mem, "mem")? {
+        "-" => Vec::new(),
+        writes => writes
+            .split(',')
+            .map(|write| {
+                let (addr, value) = write.split_once(':').ok_or("invalid write")?;
+                Ok((hex(addr)? as usize, hex(value)? as u8))
+            })
+            .collect::<Result<_, String>>()?,
+    };
+
+    Ok(Entry {
+        cycle: cycle.parse().map_err(|_| "invalid cycle".to_string())?,
+        pc: hex(pc)? as u16,
+        opcode: hex(opcode)? as u16,
+        mnemonic: mnemonic.to_string(),
+        before: parse_registers(before)?,
+        after: parse_registers(&words[words.len() - 5..words.len() - 2])?,
+        writes,
+        gfx: hex(field(gfx, "gfx")?)?,
+    })
+}
+
+/// Parses `v=... i=... sp=...`.
+fn parse_registers(words: &[&str]) -> Result<Registers, String> {
+    let v_text = field(words[0], "v")?;
+    let mut v = [0; 16];
+    for (x, value) in v.iter_mut().enumerate() {
+        *value = hex(v_text.get(x * 2..x * 2 + 2).ok_or("truncated registers")?)? as u8;
+    }
+
+    Ok(Registers {
+        v,
+        i: hex(field(words[1], "i")?)? as u16,
+        sp: field(words[2], "sp")?
+            .parse()
+            .map_err(|_| "invalid stack depth".to_string())?,
+    })
+}
+
+/// The value of a `name=value` word.
+fn field<'a>(word: &'a str, name: &str) -> Result<&'a str, String> {
+    word.strip_prefix(name)
+        .and_then(|rest| rest.strip_prefix('='))
+        .ok_or_else(|| format!("expected {}=", name))
+}
+
+fn hex(text: &str) -> Result<u64, String> {
+    u64::from_str_radix(text, 16).map_err(|_| format!("invalid hex number {}", text))
+}
+
+fn parse_json(line: &str) -> Result<Entry, String> {
+    let mut parser = Parser {
+        text: line.as_bytes(),
+        pos: 0,
+    };
+    let json = parser.value()?;
+
+    let registers = |json: &Json| {
+        let mut v = [0; 16];
+        let values = json.get("v")?.array()?;
+        if values.len() != 16 {
+            return Err("expected 16 V registers".to_string());
+        }
+        for (x, value) in values.iter().enumerate() {
+            v[x] = value.number()? as u8;
+        }
+
+        Ok::<_, String>(Registers {
+            v,
+            i: json.get("i")?.number()? as u16,
+            sp: json.get("sp")?.number()? as usize,
+        })
+    };
+    let writes = json
+        .get("writes")?
+        .array()?
+        .iter()
+        .map(|write| match write.array()? {
+            [addr, value] => Ok((addr.number()? as usize, value.number()? as u8)),
+            _ => Err("expected [address, value]".to_string()),
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(Entry {
+        cycle: json.get("cycle")?.number()?,
+        pc: json.get("pc")?.number()? as u16,
+        opcode: json.get("opcode")?.number()? as u16,
+        mnemonic: json.get("mnemonic")?.string()?.to_string(),
+        before: registers(json.get("before")?)?,
+        after: registers(json.get("after")?)?,
+        writes,
+        gfx: hex(json.get("gfx")?.string()?)?,
+    })
+}
+
+/// The subset of JSON used by traces: no floats, booleans or nulls.
+enum Json {
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> Result<&Json, String> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value)
+                .ok_or_else(|| format!("missing {}", key)),
+            _ => Err(format!("expected an object with {}", key)),
+        }
+    }
+
+    fn number(&self) -> Result<u64, String> {
+        match self {
+            Json::Number(n) => Ok(*n),
+            _ => Err("expected a number".to_string()),
+        }
+    }
+
+    fn string(&self) -> Result<&str, String> {
+        match self {
+            Json::String(s) => Ok(s),
+            _ => Err("expected a string".to_string()),
+        }
+    }
+
+    fn array(&self) -> Result<&[Json], String> {
+        match self {
+            Json::Array(values) => Ok(values),
+            _ => Err("expected an array".to_string()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn value(&mut self) -> Result<Json, String> {
+        match self.peek()? {
+            b'{' => {
+                let mut fields = Vec::new();
+                self.pos += 1;
+                while self.peek()? != b'}' {
+                    if !fields.is_empty() {
+                        self.expect(b',')?;
+                    }
+                    let name = self.string()?;
+                    self.expect(b':')?;
+                    fields.push((name, self.value()?));
+                }
+                self.pos += 1;
+
+                Ok(Json::Object(fields))
+            }
+            b'[' => {
+                let mut values = Vec::new();
+                self.pos += 1;
+                while self.peek()? != b']' {
+                    if !values.is_empty() {
+                        self.expect(b',')?;
+                    }
+                    values.push(self.value()?);
+                }
+                self.pos += 1;
+
+                Ok(Json::Array(values))
+            }
+            b'"' => Ok(Json::String(self.string()?)),
+            b'0'..=b'9' => {
+                let start = self.pos;
+                while self.text.get(self.pos).is_some_and(u8::is_ascii_digit) {
+                    self.pos += 1;
+                }
+                let digits = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+
+                digits
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| format!("invalid number {}", digits))
+            }
+            c => Err(format!("unexpected {:?}", c as char)),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+
+        loop {
+            match self.text.get(self.pos) {
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    bytes.push(*self.text.get(self.pos + 1).ok_or("truncated string")?);
+                    self.pos += 2;
+                }
+                Some(&c) => {
+                    bytes.push(c);
+                    self.pos += 1;
+                }
+                None => return Err("truncated string".to_string()),
+            }
+        }
+        self.pos += 1;
+
+        String::from_utf8(bytes).map_err(|_| "invalid string".to_string())
+    }
+
+    /// The next byte which is not whitespace.
+    fn peek(&mut self) -> Result<u8, String> {
+        while self.text.get(self.pos).is_some_and(u8::is_ascii_whitespace) {
+            self.pos += 1;
+        }
+
+        self.text
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| "truncated entry".to_string())
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        if self.peek()? != c {
+            return Err(format!("expected {:?}", c as char));
+        }
+        self.pos += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    use crate::breakpoint::opcode_pattern;
+    use crate::chip8::Chip8;
+    use crate::trace::{diverge, Divergence, Entry, Filter, Format, Tracer};
+
+    // LD V0, 0x2A; LD I, 0x300; LD B, V0; JP 0x206
+    const ROM: [u8; 8] = [0x60, 0x2A, 0xA3, 0x00, 0xF0, 0x33, 0x12, 0x06];
+
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn run(tracer: Tracer, cycles: usize) -> Chip8 {
+        let mut chip8 = Chip8::from_rom(&ROM).unwrap();
+        chip8.trace = Some(tracer);
+        for _ in 0..cycles {
+            chip8.execute().unwrap();
+        }
+        chip8
+    }
+
+    #[test]
+    fn test_trace_entries() {
+        let chip8 = run(Tracer::new(), 3);
+        let recent = &chip8.trace.as_ref().unwrap().recent;
+
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0].mnemonic, "LD V0, 0x2A");
+        assert_eq!(recent[0].before.v[0], 0);
+        assert_eq!(recent[0].after.v[0], 0x2A);
+        assert_eq!(recent[2].cycle, 2);
+        assert_eq!(recent[2].writes, [(0x300, 0), (0x301, 4), (0x302, 2)]);
+        assert_eq!(
+            recent[2].to_string(),
+            "2 0204 F033 v=2A000000000000000000000000000000 i=0300 sp=0 -> \
+             v=2A000000000000000000000000000000 i=0300 sp=0 \
+             mem=0300:00,0301:04,0302:02 gfx=28C31CF8DF2EC325 ; LD B, V0"
+        );
+    }
+
+    #[test]
+    fn test_trace_self_modifying() {
+        // I = 0x202, LD B, V0 overwriting itself
+        let mut chip8 = Chip8::from_rom(&[0xA2, 0x02, 0xF0, 0x33]).unwrap();
+        chip8.trace = Some(Tracer::new());
+        chip8.execute().unwrap();
+        chip8.execute().unwrap();
+
+        let entry = &chip8.trace.unwrap().recent[1];
+        assert_eq!(entry.writes, [(0x202, 0), (0x203, 0), (0x204, 0)]);
+        assert_eq!(
+            (entry.opcode, entry.mnemonic.as_str()),
+            (0xF033, "LD B, V0")
+        );
+    }
+
+    #[test]
+    fn test_trace_output() {
+        for format in [Format::Text, Format::Json] {
+            let out = Shared::default();
+            let mut tracer = Tracer::with_output(Box::new(out.clone()), format);
+            tracer.capacity = 2;
+            let chip8 = run(tracer, 5);
+
+            let text = String::from_utf8(out.0.borrow().clone()).unwrap();
+            let entries: Vec<Entry> = text.lines().map(|l| Entry::parse(l).unwrap()).collect();
+            let recent = &chip8.trace.as_ref().unwrap().recent;
+
+            assert_eq!(entries.len(), 5);
+            assert_eq!(recent.len(), 2);
+            assert_eq!(entries[3..], recent.iter().cloned().collect::<Vec<_>>()[..]);
+        }
+    }
+
+    #[test]
+    fn test_trace_filter() {
+        let mut tracer = Tracer::new();
+        tracer.filter = Filter {
+            pc: Some((0x202, 0x206)),
+            opcodes: vec![
+                opcode_pattern("1nnn").unwrap(),
+                opcode_pattern("Annn").unwrap(),
+            ],
+        };
+        let chip8 = run(tracer, 5);
+
+        let pcs: Vec<u16> = chip8.trace.unwrap().recent.iter().map(|e| e.pc).collect();
+        assert_eq!(pcs, [0x202, 0x206, 0x206]);
+    }
+
+    #[test]
+    fn test_diverge() {
+        let a: Vec<Entry> = run(Tracer::new(), 5).trace.unwrap().recent.into();
+        assert_eq!(diverge(&a, &a), None);
+
+        let mut b = a.clone();
+        b[2].after.v[3] = 6;
+        b[2].writes[1].1 = 5;
+        b[2].mnemonic = "LD B, V0 ; BCD".to_string();
+        assert_eq!(
+            diverge(&a, &b),
+            Some(Divergence {
+                index: 2,
+                differences: vec![
+                    "V3 after: 00 != 06".to_string(),
+                    "memory writes: 0300:00,0301:04,0302:02 != 0300:00,0301:05,0302:02".to_string()
+                ]
+            })
+        );
+
+        assert_eq!(
+            diverge(&a[..4], &a),
+            Some(Divergence {
+                index: 4,
+                differences: vec!["first trace ended".to_string()]
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let entry = run(Tracer::new(), 1).trace.unwrap().recent[0].clone();
+
+        assert!(Entry::parse(&entry.to_string()[1..]).is_err());
+        assert!(Entry::parse(&entry.to_json()[..40]).is_err());
+        assert!(Entry::parse("").is_err());
+    }
+}
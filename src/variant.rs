@@ -0,0 +1,38 @@
+use crate::mem::{RAM_SIZE, XO_RAM_SIZE};
+
+/// Instruction set understood by the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    /// The original CHIP-8 of the COSMAC VIP.
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1: 128x64 hi-res mode, scrolling, 16x16 sprites, large
+    /// font and RPL flags.
+    SuperChip,
+    /// XO-CHIP, a superset of SUPER-CHIP: 64 KiB of memory, two bitplanes,
+    /// register ranges and an audio pattern buffer.
+    XoChip,
+}
+
+impl Variant {
+    pub const ALL: [(&'static str, Variant); 3] = [
+        ("CHIP-8", Variant::Chip8),
+        ("SUPER-CHIP", Variant::SuperChip),
+        ("XO-CHIP", Variant::XoChip),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        Variant::ALL
+            .iter()
+            .find(|(_, variant)| variant == self)
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+
+    pub fn ram_size(&self) -> usize {
+        match self {
+            Variant::XoChip => XO_RAM_SIZE,
+            _ => RAM_SIZE,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Runs the ROMs of `tests/roms` headlessly and compares their final display
+//! with the PBM images of `tests/golden`.
+//!
+//! After checking the output of a new or changed ROM by hand, run with
+//! `CHIPR_BLESS=1` to write its golden image.
+//!
+//! The Timendus chip8-test-suite is checked against its published
+//! screenshots instead, which are never blessed. It is not vendored, so its
+//! tests are ignored until it is copied in as `tests/roms/README.md`
+//! describes.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use chipr::display::{ascii_art, write_pbm};
+use chipr::{Chip8, Clock, Quirks, Variant};
+
+/// Ten seconds of emulated time, test ROMs halt well before.
+const MAX_FRAMES: usize = 600;
+/// Byte the Timendus ROMs with a menu read as the choice made, to run without
+/// input.
+const MENU_CHOICE: u16 = 0x1FF;
+
+fn check(name: &str, quirks: Quirks) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let rom = fs::read(dir.join("roms").join(format!("{}.ch8", name))).unwrap();
+
+    let mut chip8 = Chip8::from_rom(&rom).unwrap();
+    chip8.quirks = quirks;
+    chip8.paused = false;
+
+    let mut clock = Clock::default();
+    for _ in 0..MAX_FRAMES {
+        if chip8.is_halted() {
+            break;
+        }
+
+        clock.frame(&mut chip8).unwrap();
+    }
+    assert!(chip8.is_halted(), "{} did not halt", name);
+
+    let mut image = Vec::new();
+    write_pbm(&mut image, chip8.framebuffer(), chip8.width()).unwrap();
+
+    let golden_path = dir.join("golden").join(format!("{}.pbm", name));
+    if env::var_os("CHIPR_BLESS").is_some() {
+        fs::write(&golden_path, &image).unwrap();
+        return;
+    }
+
+    let golden = fs::read(&golden_path).unwrap();
+    assert!(
+        image == golden,
+        "{} does not match {}:\n{}",
+        name,
+        golden_path.display(),
+        ascii_art(chip8.framebuffer(), chip8.width())
+    );
+}
+
+/// Hex digits drawn with `Fx29`, and the BCD digits of 234 from `Fx33`.
+#[test]
+fn digits() {
+    check("digits", Quirks::COSMAC_VIP);
+}
+
+/// Skips, nested calls, `Bnnn`, `Fx1E`, `Fx55`/`Fx65` and sprite collision,
+/// a check mark per passed test and a cross per failed one.
+#[test]
+fn branches() {
+    check("branches", Quirks::COSMAC_VIP);
+}
+
+/// Results and VF of `8xy1` to `8xyE` and `7xkk`, with VF also as the
+/// destination and as an operand, a check mark per passed test.
+#[test]
+fn flags() {
+    check("flags", Quirks::COSMAC_VIP);
+}
+
+/// Runs a ROM of the Timendus suite for `MAX_FRAMES` frames, or until it
+/// halts, and compares its display pixel by pixel with the screenshot
+/// `golden` in `tests/golden/timendus`.
+fn check_suite(name: &str, golden: &str, variant: Variant, quirks: Quirks, choice: Option<u8>) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let rom = fs::read(dir.join("roms/timendus").join(format!("{}.ch8", name))).unwrap();
+
+    let mut chip8 = Chip8::from_rom_with_variant(&rom, variant).unwrap();
+    chip8.quirks = quirks;
+    chip8.paused = false;
+    if let Some(choice) = choice {
+        chip8.mem.set(MENU_CHOICE, choice).unwrap();
+    }
+
+    let mut clock = Clock::default();
+    for _ in 0..MAX_FRAMES {
+        if chip8.is_halted() {
+            break;
+        }
+
+        clock.frame(&mut chip8).unwrap();
+    }
+
+    let golden_path = dir.join("golden/timendus").join(format!("{}.pbm", golden));
+    let golden = read_pbm(&fs::read(&golden_path).unwrap());
+    let pixels: Vec<bool> = chip8.framebuffer().iter().map(|&p| p != 0).collect();
+    assert!(
+        golden == (chip8.width(), pixels),
+        "{} does not match {}:\n{}",
+        name,
+        golden_path.display(),
+        ascii_art(chip8.framebuffer(), chip8.width())
+    );
+}
+
+/// The width and pixels of a plain (P1) or raw (P4) PBM image, as converted
+/// from a screenshot.
+fn read_pbm(data: &[u8]) -> (usize, Vec<bool>) {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while fields.len() < 3 {
+        while data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if data[pos] == b'#' {
+            while data[pos] != b'\n' {
+                pos += 1;
+            }
+            continue;
+        }
+
+        let start = pos;
+        while !data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        fields.push(std::str::from_utf8(&data[start..pos]).unwrap());
+    }
+
+    let width: usize = fields[1].parse().unwrap();
+    let height: usize = fields[2].parse().unwrap();
+    let pixels = match fields[0] {
+        "P1" => data[pos..]
+            .iter()
+            .filter(|b| matches!(b, b'0' | b'1'))
+            .map(|&b| b == b'1')
+            .collect(),
+        "P4" => {
+            let row_bytes = width.div_ceil(8);
+            let rows = &data[pos + 1..];
+            (0..width * height)
+                .map(|n| {
+                    let (y, x) = (n / width, n % width);
+                    rows[y * row_bytes + x / 8] & (0x80 >> (x % 8)) != 0
+                })
+                .collect()
+        }
+        magic => panic!("not a PBM image: {}", magic),
+    };
+
+    (width, pixels)
+}
+
+#[test]
+#[ignore = "needs the Timendus suite in tests/roms/timendus"]
+fn timendus_chip8_logo() {
+    check_suite(
+        "1-chip8-logo",
+        "1-chip8-logo",
+        Variant::Chip8,
+        Quirks::COSMAC_VIP,
+        None,
+    );
+}
+
+#[test]
+#[ignore = "needs the Timendus suite in tests/roms/timendus"]
+fn timendus_ibm_logo() {
+    check_suite(
+        "2-ibm-logo",
+        "2-ibm-logo",
+        Variant::Chip8,
+        Quirks::COSMAC_VIP,
+        None,
+    );
+}
+
+#[test]
+#[ignore = "needs the Timendus suite in tests/roms/timendus"]
+fn timendus_corax_plus() {
+    check_suite(
+        "3-corax+",
+        "3-corax+",
+        Variant::Chip8,
+        Quirks::COSMAC_VIP,
+        None,
+    );
+}
+
+#[test]
+#[ignore = "needs the Timendus suite in tests/roms/timendus"]
+fn timendus_flags() {
+    check_suite(
+        "4-flags",
+        "4-flags",
+        Variant::Chip8,
+        Quirks::COSMAC_VIP,
+        None,
+    );
+}
+
+/// The quirks test, once for each platform of its menu.
+#[test]
+#[ignore = "needs the Timendus suite in tests/roms/timendus"]
+fn timendus_quirks() {
+    let platforms = [
+        ("chip8", Variant::Chip8, Quirks::COSMAC_VIP),
+        ("schip", Variant::SuperChip, Quirks::SUPER_CHIP),
+        ("xochip", Variant::XoChip, Quirks::XO_CHIP),
+    ];
+
+    for (n, (platform, variant, quirks)) in platforms.into_iter().enumerate() {
+        let golden = format!("5-quirks-{}", platform);
+        check_suite("5-quirks", &golden, variant, quirks, Some(n as u8 + 1));
+    }
+}